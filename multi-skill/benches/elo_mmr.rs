@@ -0,0 +1,70 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use multi_skill::data_processing::Contest;
+use multi_skill::systems::{EloMMR, PlayersByName, TanhTerm, TanhTerms, simulate_contest};
+use std::hint::black_box;
+
+fn make_terms(n: usize) -> Vec<TanhTerm> {
+    (0..n)
+        .map(|i| TanhTerm {
+            mu: 1500. + 300. * (i as f64 * 0.37).sin(),
+            w_arg: 0.005 + 0.001 * (i % 7) as f64,
+            w_out: 0.01 + 0.002 * (i % 5) as f64,
+        })
+        .collect()
+}
+
+// Compares the scalar and batched evaluations of the sum of logistic terms
+fn bench_tanh_terms(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tanh_terms");
+    for n in [100, 1000, 10_000] {
+        let terms = make_terms(n);
+        let batch: TanhTerms = terms.iter().map(|&term| (term, 1.)).collect();
+        group.bench_with_input(BenchmarkId::new("scalar", n), &terms, |b, terms| {
+            b.iter(|| {
+                terms.iter().fold((0., 0.), |(s, sp), term| {
+                    let (v, vp) = term.base_values(black_box(1600.));
+                    (s + v, sp + vp)
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("batch", n), &batch, |b, batch| {
+            b.iter(|| batch.sum_base_values(black_box(1600.)))
+        });
+    }
+    group.finish();
+}
+
+// Rates a sequence of contests among the same players, so that most of them have histories
+fn bench_round_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("elo_mmr_round_update");
+    group.sample_size(10);
+    for n in [100, 1000, 5000] {
+        let contests: Vec<Contest> = (0..5)
+            .map(|index| {
+                let mut contest = Contest::new(index);
+                for i in 0..n {
+                    contest.push_contestant(format!("player{}", (i * 7 + index * 13) % n));
+                }
+                contest
+            })
+            .collect();
+        for (name, system) in [
+            ("logistic", EloMMR::default()),
+            ("tabulated", EloMMR::default_tabulated()),
+        ] {
+            group.bench_with_input(BenchmarkId::new(name, n), &contests, |b, contests| {
+                b.iter(|| {
+                    let mut players = PlayersByName::new();
+                    for (index, contest) in contests.iter().enumerate() {
+                        simulate_contest(&mut players, contest, &system, 1500., 350., index);
+                    }
+                    players
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_tanh_terms, bench_round_update);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use multi_skill::data_processing::Contest;
+use multi_skill::systems::{PlayersByName, TrueSkillSPb, simulate_contest};
+
+// Rates a single large contest with ties, passing each layer's messages either on one thread
+// or in parallel on all of them
+fn bench_round_update(c: &mut Criterion) {
+    let mut group = c.benchmark_group("true_skill_round_update");
+    group.sample_size(10);
+    let sequential = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    let parallel = rayon::ThreadPoolBuilder::new().build().unwrap();
+    for n in [1000, 10_000, 30_000] {
+        let mut contest = Contest::new(0);
+        for i in 0..n {
+            contest.push_contestant(format!("player{}", i));
+        }
+        // Tie every tenth pair of neighbours
+        for i in (0..n - 1).step_by(10) {
+            let (lo, hi) = (contest.standings[i].1, contest.standings[i + 1].2);
+            contest.standings[i].2 = hi;
+            contest.standings[i + 1].1 = lo;
+        }
+        let system = TrueSkillSPb::default();
+        for (name, pool) in [("sequential", &sequential), ("parallel", &parallel)] {
+            group.bench_with_input(BenchmarkId::new(name, n), &contest, |b, contest| {
+                b.iter(|| {
+                    pool.install(|| {
+                        let mut players = PlayersByName::new();
+                        simulate_contest(&mut players, contest, &system, 1500., 350., 0);
+                        players
+                    })
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_round_update);
+criterion_main!(benches);
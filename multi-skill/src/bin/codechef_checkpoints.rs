@@ -1,4 +1,4 @@
-use multi_skill::data_processing::{get_dataset_by_name, try_write_slice_to_file};
+use multi_skill::data_processing::{Contest, get_dataset_by_name, try_write_slice_to_file};
 use multi_skill::metrics::compute_metrics_custom;
 use multi_skill::summary::make_leaderboard;
 use multi_skill::systems::{Player, PlayersByName, get_rating_system_by_name, simulate_contest};
@@ -38,7 +38,7 @@ fn main() {
     let mut mu_noob = 1500.;
     let sig_noob = 350.;
     let mut players = std::collections::HashMap::new();
-    let mut avg_perf = compute_metrics_custom(&mut players, &[]);
+    let mut avg_perf = compute_metrics_custom(&mut players, &Contest::new(0), &*system);
 
     // Get list of contest names to compare with Codechef's rating system
     let paths = std::fs::read_dir("/home/work_space/elommr-data/ratings").unwrap();
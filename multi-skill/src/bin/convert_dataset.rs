@@ -0,0 +1,41 @@
+use multi_skill::data_processing::{
+    ARCHIVE_EXTENSION, Dataset, get_dataset_by_name, write_archive, write_json,
+};
+use std::path::Path;
+
+// Converts a cache directory of JSON files into a single archive file, or vice versa
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 2 {
+        tracing::error!(
+            "Usage: {} dataset_name OR {} dataset_name.{}",
+            args[0],
+            args[0],
+            ARCHIVE_EXTENSION
+        );
+        return;
+    }
+    let dataset_name = &args[1];
+    let dataset = get_dataset_by_name(dataset_name).unwrap();
+
+    let source = Path::new("../cache").join(dataset_name);
+    if source.extension() == Some(ARCHIVE_EXTENSION.as_ref()) {
+        let dest = source.with_extension("");
+        if dest.exists() {
+            tracing::error!("Refusing to overwrite {:?}", dest);
+            return;
+        }
+        std::fs::create_dir_all(&dest).expect("Could not create directory");
+        for (index, contest) in dataset.iter().enumerate() {
+            write_json(&contest, dest.join(format!("{}.json", index)))
+                .expect("Failed to write contest");
+        }
+        tracing::info!("Wrote {} contests to {:?}", dataset.len(), dest);
+    } else {
+        let dest = source.with_extension(ARCHIVE_EXTENSION);
+        write_archive(&dataset, &dest).expect("Failed to write archive");
+        tracing::info!("Wrote {} contests to {:?}", dataset.len(), dest);
+    }
+}
@@ -141,6 +141,10 @@ fn write_round(
             rating_params: Default::default(),
             time_seconds: datetime.timestamp() as u64,
             standings: process_round(round),
+            teams: Default::default(),
+            scores: Default::default(),
+            category: None,
+            unrated: Default::default(),
         };
         std::fs::create_dir_all("../cache/dance").expect("Could not create cache directory");
         let path = format!("../cache/dance/{}.json", num_rounds);
@@ -10,8 +10,14 @@ fn main() {
     let methods = vec![
         // "glicko",
         // "bar",
-        "cfsys", "tcsys", // "trueskill",
-        "mmx-fast", "mmr-fast",
+        // "bar-pl",
+        // "bar-tm",
+        // "endure",
+        "cfsys",
+        "tcsys",
+        "trueskill",
+        "mmx-fast",
+        "mmr-fast",
     ];
     let metrics = vec!["acc", "rnk"];
 
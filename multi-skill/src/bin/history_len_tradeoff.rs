@@ -0,0 +1,48 @@
+use multi_skill::data_processing::{Dataset, get_dataset_by_name};
+use multi_skill::experiment_config::Experiment;
+use multi_skill::systems::EloMMR;
+
+// Measures how the accuracy and speed of Elo-MMR's logistic variant depend on the number of
+// past performances that each player's rating remembers
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 2 && args.len() != 3 {
+        tracing::error!("Usage: {} dataset_name [num_contests]", args[0]);
+        return;
+    }
+    let history_lens = [1, 2, 5, 10, 20, 50, 100, 200, 500, usize::MAX];
+
+    // To ensure accurate timings, this loop is not parallelized
+    for history_len in history_lens {
+        let mut dataset = get_dataset_by_name(&args[1]).unwrap();
+        if let Some(num_contests) = args.get(2).and_then(|s| s.parse::<usize>().ok()) {
+            let num_contests = num_contests.min(dataset.len());
+            dataset = dataset.subrange(0..num_contests).boxed();
+        }
+        let experiment = Experiment {
+            mu_noob: 1500.,
+            sig_noob: 350.,
+            system: Box::new(EloMMR {
+                history_len,
+                ..EloMMR::default_fast()
+            }),
+            dataset,
+            loaded_state: std::collections::HashMap::new(),
+            contests_before: 0,
+            contests_processed: Some(0),
+            save_checkpoint: None,
+            seeds: std::collections::HashMap::new(),
+        };
+        let train_set_len = experiment.dataset.len() / 10;
+        let results = experiment.eval(train_set_len);
+
+        tracing::info!(
+            "history_len={}: {}, {}s",
+            history_len,
+            results.avg_perf,
+            results.secs_elapsed
+        );
+    }
+}
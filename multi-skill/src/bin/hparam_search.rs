@@ -65,6 +65,7 @@ fn main() {
                 // make the algorithm fast
                 let subsample_size = 256;
                 let subsample_bucket = 1.;
+                let history_len = 256;
 
                 // Gaussian performance model
                 let system = systems::EloMMR {
@@ -75,7 +76,30 @@ fn main() {
                     split_ties,
                     subsample_size,
                     subsample_bucket,
+                    subsample_mode: systems::SubsampleMode::Window,
+                    history_len,
                     variant: systems::EloMMRVariant::Gaussian,
+                    table_tolerance: None,
+                    margin_sig: None,
+                    inactivity_decay: None,
+                };
+                systems.push(Box::new(system));
+
+                // Gaussian performance model, with uncertainties from the likelihood's curvature
+                let system = systems::EloMMR {
+                    weight_limit,
+                    noob_delay: vec![],
+                    sig_limit,
+                    drift_per_day: 0.,
+                    split_ties,
+                    subsample_size,
+                    subsample_bucket,
+                    subsample_mode: systems::SubsampleMode::Window,
+                    history_len,
+                    variant: systems::EloMMRVariant::GaussianExact,
+                    table_tolerance: None,
+                    margin_sig: None,
+                    inactivity_decay: None,
                 };
                 systems.push(Box::new(system));
 
@@ -90,7 +114,12 @@ fn main() {
                         split_ties,
                         subsample_size,
                         subsample_bucket,
+                        subsample_mode: systems::SubsampleMode::Window,
+                        history_len,
                         variant: systems::EloMMRVariant::Logistic(rho),
+                        table_tolerance: None,
+                        margin_sig: None,
+                        inactivity_decay: None,
                     };
                     systems.push(Box::new(system));
                 }
@@ -108,6 +137,24 @@ fn main() {
                 kappa: 1e-4,
             };
             systems.push(Box::new(system));
+
+            let system = systems::PlackettLuce {
+                beta,
+                sig_drift,
+                kappa: 1e-4,
+            };
+            systems.push(Box::new(system));
+
+            let system = systems::ThurstoneMosteller {
+                beta,
+                sig_drift,
+                kappa: 1e-4,
+                eps: 1.,
+            };
+            systems.push(Box::new(system));
+
+            let system = systems::EndureElo { beta, sig_drift };
+            systems.push(Box::new(system));
         }
     }
 
@@ -124,10 +171,24 @@ fn main() {
             system,
             dataset,
             loaded_state: std::collections::HashMap::new(),
+            contests_before: 0,
+            contests_processed: Some(0),
             save_checkpoint: None,
+            seeds: std::collections::HashMap::new(),
         };
         let results = experiment.eval(0);
 
+        // Reject parameter settings that cause numerical trouble
+        let failures = results.contests_with_solver_failures();
+        if failures > 0 {
+            tracing::warn!(
+                "Rejecting {:?}: solver failed to converge in {} contests",
+                experiment.system,
+                failures
+            );
+            return;
+        }
+
         let horizontal = "============================================================";
         tracing::info!(
             "{:?}: {}, {}s, {} contests\n{}",
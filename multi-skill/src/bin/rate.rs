@@ -33,7 +33,10 @@ fn get_experiment_from_args(args: &[String]) -> (Experiment, String) {
             system,
             dataset,
             loaded_state: std::collections::HashMap::new(),
+            contests_before: 0,
+            contests_processed: Some(0),
             save_checkpoint: None,
+            seeds: std::collections::HashMap::new(),
         };
         (experiment, source.to_string())
     }
@@ -0,0 +1,53 @@
+use multi_skill::data_processing::{get_dataset_by_name, write_slice_to_file};
+use multi_skill::experiment_config::{ExperimentConfig, read_checkpoint};
+use multi_skill::smoothing::HistorySmoother;
+
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    // Parse arguments, load the checkpoint, and look up the times and weights of its contests
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 3 {
+        tracing::error!("Usage: {} config_file checkpoint_file", args[0]);
+        return;
+    }
+    // The checkpoint must have been rated with this config, whose system determines the
+    // transitions and whose dataset determines the contests that its events refer to
+    let config = ExperimentConfig::from_file(&args[1]);
+    let system = match config.system.elo_mmr() {
+        Some(system) => system,
+        None => {
+            tracing::error!("Smoothing is only supported for Elo-MMR systems");
+            return;
+        }
+    };
+    let smoother = HistorySmoother { system };
+    let players = read_checkpoint(&args[2])
+        .expect("Failed to read checkpoint")
+        .players;
+    let dataset = get_dataset_by_name(&config.contest_source).unwrap();
+    let contests: Vec<(u64, f64)> = config
+        .filter
+        .apply(dataset)
+        .expect("Invalid contest filter")
+        .iter()
+        .map(|contest| (contest.time_seconds, contest.rating_params.weight))
+        .collect();
+
+    let dir = std::path::PathBuf::from("../data").join(&config.contest_source);
+    std::fs::create_dir_all(dir.join("smoothed_players")).expect("Could not create directory");
+
+    // Print smoothed contest histories to data/{source}/smoothed_players/{handle}.csv,
+    // in the same format as the filtered histories that the rate binary prints
+    for (handle, player) in &players {
+        let bad_chars: &[char] = &['/', '\\'];
+        let handle = handle.replace(bad_chars, "-");
+        let history = smoother
+            .smooth_player(&player.borrow(), &contests)
+            .expect("Checkpoint doesn't match the config's contests");
+
+        let player_file = dir.join(format!("smoothed_players/{}.csv", handle));
+        write_slice_to_file(&history, &player_file).expect("Failed write");
+    }
+    tracing::info!("Smoothed the histories of {} players", players.len());
+}
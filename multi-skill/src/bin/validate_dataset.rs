@@ -0,0 +1,52 @@
+use multi_skill::data_processing::{
+    ARCHIVE_EXTENSION, get_dataset_by_name, validate_dataset, write_archive, write_json,
+};
+use std::path::Path;
+
+// Reports every malformed contest in a dataset, and optionally writes a repaired copy of it
+fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 2 && args.len() != 3 {
+        tracing::error!("Usage: {} dataset_name [repaired_dataset_name]", args[0]);
+        return;
+    }
+    let dataset = get_dataset_by_name(&args[1]).unwrap();
+
+    let violations = validate_dataset(&dataset);
+    for (index, violation) in &violations {
+        tracing::warn!("Contest {}: {}", index, violation);
+    }
+    let num_unrepairable = violations
+        .iter()
+        .filter(|(_, v)| !v.is_repairable())
+        .count();
+    tracing::info!(
+        "Found {} violations, of which {} can't be repaired automatically",
+        violations.len(),
+        num_unrepairable
+    );
+
+    if let Some(repaired_name) = args.get(2) {
+        let dest = Path::new("../cache").join(repaired_name);
+        if dest.exists() {
+            tracing::error!("Refusing to overwrite {:?}", dest);
+            return;
+        }
+        let repaired = dataset.map(|mut contest| {
+            contest.repair();
+            contest
+        });
+        if dest.extension() == Some(ARCHIVE_EXTENSION.as_ref()) {
+            write_archive(&repaired, &dest).expect("Failed to write archive");
+        } else {
+            std::fs::create_dir_all(&dest).expect("Could not create directory");
+            for (index, contest) in repaired.iter().enumerate() {
+                write_json(&contest, dest.join(format!("{}.json", index)))
+                    .expect("Failed to write contest");
+            }
+        }
+        tracing::info!("Wrote the repaired dataset to {:?}", dest);
+    }
+}
@@ -0,0 +1,171 @@
+//! A single-file alternative to a cache directory of JSON files. Each item is encoded as
+//! MessagePack and compressed separately, so that any one of them can be read on its own.
+//!
+//! Layout: the compressed items back to back, followed by a footer containing the offset at
+//! which each item starts, the offset at which the last item ends, the number of items, and a
+//! magic tag. All footer entries are little-endian u64's.
+use super::{Dataset, Wrap};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use serde::{Serialize, de::DeserializeOwned};
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The file extension by which `get_dataset_by_name()` recognizes archives
+pub const ARCHIVE_EXTENSION: &str = "archive";
+const MAGIC: u64 = u64::from_le_bytes(*b"MSKARCH1");
+
+/// A `Dataset` stored in an archive file. Created using `get_dataset_from_archive()`.
+pub struct ArchiveDataset<T> {
+    file: Mutex<File>,
+    // offsets[i]..offsets[i + 1] is the byte range of the i'th item
+    offsets: Vec<u64>,
+    item_type: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> Dataset for ArchiveDataset<T> {
+    type Item = T;
+
+    fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    fn get(&self, index: usize) -> T {
+        let (start, end) = (self.offsets[index], self.offsets[index + 1]);
+        let mut compressed = vec![0; (end - start) as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(start))
+                .and_then(|_| file.read_exact(&mut compressed))
+                .expect("Failed to read archive");
+        }
+        let mut encoded = vec![];
+        ZlibDecoder::new(compressed.as_slice())
+            .read_to_end(&mut encoded)
+            .expect("Corrupt archive");
+        rmp_serde::from_slice(&encoded).expect("Corrupt archive")
+    }
+}
+
+fn read_u64s(file: &mut File, from_end: u64, count: usize) -> std::io::Result<Vec<u64>> {
+    let mut bytes = vec![0; 8 * count];
+    file.seek(SeekFrom::End(-(from_end as i64)))?;
+    file.read_exact(&mut bytes)?;
+    Ok(bytes
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+fn open_archive<T>(path: &Path) -> std::io::Result<ArchiveDataset<T>> {
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidData, "not an archive");
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+    if file_len < 16 {
+        return Err(invalid());
+    }
+    let [length, magic] = read_u64s(&mut file, 16, 2)?[..] else {
+        unreachable!()
+    };
+    let footer_len = length
+        .checked_add(3)
+        .and_then(|words| words.checked_mul(8))
+        .filter(|&footer_len| magic == MAGIC && footer_len <= file_len)
+        .ok_or_else(invalid)?;
+    let offsets = read_u64s(&mut file, footer_len, length as usize + 1)?;
+    if !offsets.is_sorted() || offsets[length as usize] > file_len - footer_len {
+        return Err(invalid());
+    }
+    Ok(ArchiveDataset {
+        file: Mutex::new(file),
+        offsets,
+        item_type: PhantomData,
+    })
+}
+
+/// Helper function to get data that is stored inside an archive file.
+pub fn get_dataset_from_archive<T: DeserializeOwned>(
+    archive_file: impl AsRef<Path>,
+) -> Wrap<ArchiveDataset<T>> {
+    let archive_file = archive_file.as_ref();
+    let dataset = open_archive(archive_file)
+        .unwrap_or_else(|e| panic!("There's no valid archive at {:?}: {}", archive_file, e));
+    tracing::info!("Found {} items at {:?}", dataset.len(), archive_file);
+    dataset.wrap()
+}
+
+/// Writes every item of a dataset into an archive file, replacing any existing file.
+pub fn write_archive<D: Dataset + ?Sized>(
+    dataset: &D,
+    archive_file: impl AsRef<Path>,
+) -> Result<(), String>
+where
+    D::Item: Serialize,
+{
+    let file = File::create(archive_file.as_ref()).map_err(|e| e.to_string())?;
+    let mut writer = BufWriter::new(file);
+    let mut offsets = vec![0];
+    for index in 0..dataset.len() {
+        // Named fields keep the encoding compatible with #[serde(flatten)] and skipped fields
+        let encoded = rmp_serde::to_vec_named(&dataset.get(index)).map_err(|e| e.to_string())?;
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&encoded).map_err(|e| e.to_string())?;
+        let compressed = encoder.finish().map_err(|e| e.to_string())?;
+        writer.write_all(&compressed).map_err(|e| e.to_string())?;
+        offsets.push(offsets[index] + compressed.len() as u64);
+    }
+
+    offsets.extend([dataset.len() as u64, MAGIC]);
+    for word in offsets {
+        writer
+            .write_all(&word.to_le_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_processing::Contest;
+
+    #[test]
+    fn test_archive_dataset() {
+        let archive_file = "temp_archive_of_contests.archive";
+        let contests: Vec<Contest> = (0..5)
+            .map(|index| {
+                let mut contest = Contest::new(index);
+                contest.rating_params.weight = 0.5 * index as f64;
+                for i in 0..index {
+                    contest.push_contestant(format!("player{}", i));
+                }
+                contest
+            })
+            .collect();
+        write_archive(contests.as_slice(), archive_file).unwrap();
+
+        // Random access, as well as combinators, should behave as on the original dataset
+        let dataset = get_dataset_from_archive::<Contest>(archive_file);
+        assert_eq!(dataset.len(), contests.len());
+        for idx in [3, 0, 4, 1, 2] {
+            let contest = dataset.get(idx);
+            assert_eq!(contest.name, contests[idx].name);
+            assert_eq!(
+                contest.rating_params.weight,
+                contests[idx].rating_params.weight
+            );
+            assert_eq!(contest.standings, contests[idx].standings);
+        }
+        let sizes = dataset
+            .subrange(1..4)
+            .map(|contest| contest.standings.len());
+        assert_eq!(sizes.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // Trash the archive
+        std::fs::remove_file(archive_file).unwrap();
+    }
+}
@@ -113,6 +113,10 @@ impl TryFrom<Vec<CFRatingChange>> for Contest {
             rating_params: Default::default(),
             time_seconds,
             standings,
+            teams: Default::default(),
+            scores: Default::default(),
+            category: None,
+            unrated: Default::default(),
         })
     }
 }
@@ -54,6 +54,7 @@ impl TryFrom<(usize, CTFContest, &HashMap<usize, String>)> for Contest {
         let len = json_contest.scores.len();
         let mut seen_handles = HashMap::with_capacity(len);
         let mut standings = Vec::with_capacity(len);
+        let mut scores = HashMap::with_capacity(len);
 
         for (i, place) in json_contest.scores.into_iter().enumerate() {
             let mut name = match id_to_name.get(&place.team_id) {
@@ -73,6 +74,12 @@ impl TryFrom<(usize, CTFContest, &HashMap<usize, String>)> for Contest {
                 );
                 name += "_clone";
             }
+            match place.points.parse::<f64>() {
+                Ok(points) => {
+                    scores.insert(name.clone(), points);
+                }
+                Err(_) => tracing::warn!("{} has invalid points {}", url, place.points),
+            }
             standings.push((name, i, i));
         }
 
@@ -82,6 +89,10 @@ impl TryFrom<(usize, CTFContest, &HashMap<usize, String>)> for Contest {
             rating_params: Default::default(),
             time_seconds: json_contest.time.round() as u64,
             standings,
+            teams: Default::default(),
+            scores,
+            category: None,
+            unrated: Default::default(),
         })
     }
 }
@@ -1,4 +1,6 @@
 use serde::{Serialize, de::DeserializeOwned};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::ops::{Bound, RangeBounds};
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
@@ -117,6 +119,18 @@ impl<D: Dataset> Wrap<D> {
     pub fn map<T>(self, f: impl Fn(D::Item) -> T) -> Wrap<impl Dataset<Item = T>> {
         Wrap::from_closure(self.len(), move |i| f(self.get(i)))
     }
+
+    /// Keep only the elements that satisfy a predicate, preserving their order.
+    /// Every element is retrieved once, upfront, in order to index the ones that remain.
+    pub fn filter(
+        self,
+        predicate: impl Fn(&D::Item) -> bool,
+    ) -> Wrap<impl Dataset<Item = D::Item>> {
+        let indices: Vec<usize> = (0..self.len())
+            .filter(|&i| predicate(&self.get(i)))
+            .collect();
+        Wrap::from_closure(indices.len(), move |i| self.get(indices[i]))
+    }
 }
 
 impl<D: Dataset> IntoIterator for Wrap<D> {
@@ -243,6 +257,62 @@ where
     }
 }
 
+/// A `Dataset` that interleaves several others in order of a key, as in the merge step of
+/// merge sort. Created using `MergedDataset::by_key()`.
+pub struct MergedDataset<D: Dataset> {
+    sources: Vec<D>,
+    provenance: Vec<(usize, usize)>,
+}
+
+impl<D: Dataset> MergedDataset<D> {
+    /// Merges datasets that are each sorted by `key`. Ties are broken in favor of the earlier
+    /// source, and each source's own order is preserved even if it isn't sorted.
+    pub fn by_key<K: Ord>(sources: Vec<D>, key: impl Fn(&D::Item) -> K) -> Self {
+        let mut keys: Vec<_> = sources
+            .iter()
+            .map(|source| {
+                let keys: Vec<K> = (0..source.len()).map(|i| key(&source.get(i))).collect();
+                keys.into_iter()
+            })
+            .collect();
+        let mut heap: BinaryHeap<_> = keys
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(src, src_keys)| Some(Reverse((src_keys.next()?, src, 0))))
+            .collect();
+
+        let mut provenance = Vec::with_capacity(sources.iter().map(D::len).sum());
+        while let Some(Reverse((_, src, idx))) = heap.pop() {
+            provenance.push((src, idx));
+            if let Some(next_key) = keys[src].next() {
+                heap.push(Reverse((next_key, src, idx + 1)));
+            }
+        }
+        Self {
+            sources,
+            provenance,
+        }
+    }
+
+    /// For each merged index, the index of its source and its original index in that source.
+    pub fn provenance(&self) -> &[(usize, usize)] {
+        &self.provenance
+    }
+}
+
+impl<D: Dataset> Dataset for MergedDataset<D> {
+    type Item = D::Item;
+
+    fn len(&self) -> usize {
+        self.provenance.len()
+    }
+
+    fn get(&self, index: usize) -> Self::Item {
+        let (src, idx) = self.provenance[index];
+        self.sources[src].get(idx)
+    }
+}
+
 /// A `Dataset` whose use must be rate-limited, perhaps because it makes web API calls.
 pub struct PatientDataset<D: Dataset> {
     base_dataset: D,
@@ -314,6 +384,37 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_filtered_dataset() {
+        let dataset = Wrap::from_closure(10, |x| x * x).filter(|&x| x % 3 == 1);
+
+        assert_eq!(
+            dataset.iter().collect::<Vec<_>>(),
+            vec![1, 4, 16, 25, 49, 64]
+        );
+        assert_eq!(
+            dataset.subrange(2..4).iter().collect::<Vec<_>>(),
+            vec![16, 25]
+        );
+    }
+
+    #[test]
+    fn test_merged_dataset() {
+        let sources = [vec![1, 3, 5, 5], vec![], vec![2, 3, 4]];
+        let datasets = sources.iter().map(Vec::as_slice).collect();
+        let merged = MergedDataset::by_key(datasets, |&x| x);
+
+        let merged_vals: Vec<_> = (0..merged.len()).map(|i| merged.get(i)).collect();
+        assert_eq!(merged_vals, vec![1, 2, 3, 3, 4, 5, 5]);
+        assert_eq!(
+            merged.provenance(),
+            [(0, 0), (2, 0), (0, 1), (2, 1), (2, 2), (0, 2), (0, 3)]
+        );
+        for (val, &(src, idx)) in merged_vals.into_iter().zip(merged.provenance()) {
+            assert_eq!(val, sources[src][idx]);
+        }
+    }
+
     #[test]
     fn test_cached_dataset() {
         let length = 5;
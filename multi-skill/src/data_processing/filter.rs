@@ -0,0 +1,182 @@
+use super::{Contest, ContestDataset, Dataset};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// Criteria for selecting and cleaning the contests of a dataset. Every criterion is optional,
+/// and the default filter keeps everything unchanged.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ContestFilter {
+    /// Keep only contests that end at or after this many seconds from the Unix Epoch
+    pub min_time_seconds: Option<u64>,
+    /// Keep only contests that end at or before this many seconds from the Unix Epoch
+    pub max_time_seconds: Option<u64>,
+    /// Keep only contests with at least this many standings entries, after removing handles
+    pub min_participants: Option<usize>,
+    /// Keep only contests whose name matches this regular expression
+    pub name_pattern: Option<String>,
+    /// Keep only contests with at least this weight
+    pub min_weight: Option<f64>,
+    /// Keep only contests with at most this weight
+    pub max_weight: Option<f64>,
+    /// Handles to remove from all standings, such as banned accounts
+    pub removed_handles: HashSet<String>,
+    /// Gives each contest without a category the text that this regular expression matches in
+    /// its name, if it matches exactly one distinct text. For example, "Div\\. [1-4]" tags
+    /// Codeforces rounds by division, leaving rounds that are open to several divisions untagged.
+    pub category_pattern: Option<String>,
+}
+
+impl ContestFilter {
+    /// Whether this filter keeps every contest unchanged
+    pub fn is_trivial(&self) -> bool {
+        self.keeps_all() && self.removed_handles.is_empty() && self.category_pattern.is_none()
+    }
+
+    /// Whether this filter keeps every contest, though perhaps modified, so that the filtered
+    /// dataset's indices match the source's
+    pub fn keeps_all(&self) -> bool {
+        self.min_time_seconds.is_none()
+            && self.max_time_seconds.is_none()
+            && self.min_participants.is_none()
+            && self.name_pattern.is_none()
+            && self.min_weight.is_none()
+            && self.max_weight.is_none()
+    }
+
+    fn accepts(&self, contest: &Contest, name_regex: Option<&Regex>) -> bool {
+        let params = &contest.rating_params;
+        self.min_time_seconds
+            .is_none_or(|t| contest.time_seconds >= t)
+            && self
+                .max_time_seconds
+                .is_none_or(|t| contest.time_seconds <= t)
+            && self
+                .min_participants
+                .is_none_or(|n| contest.standings.len() >= n)
+            && name_regex.is_none_or(|regex| regex.is_match(&contest.name))
+            && self.min_weight.is_none_or(|w| params.weight >= w)
+            && self.max_weight.is_none_or(|w| params.weight <= w)
+    }
+
+    /// Removes the handles from and assigns categories to every contest, then keeps only the
+    /// contests that meet all of the criteria. Unless the filter is trivial, this reads the
+    /// entire dataset upfront.
+    pub fn apply(&self, dataset: ContestDataset) -> Result<ContestDataset, String> {
+        if self.is_trivial() {
+            return Ok(dataset);
+        }
+        let name_regex = match &self.name_pattern {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        let category_regex = match &self.category_pattern {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| e.to_string())?),
+            None => None,
+        };
+        let filter = self.clone();
+        let removed_handles = self.removed_handles.clone();
+        let filtered = dataset
+            .map(move |mut contest| {
+                if !removed_handles.is_empty() {
+                    contest.remove_handles(&removed_handles);
+                }
+                if let Some(regex) = &category_regex {
+                    contest.categorize_by_name(regex);
+                }
+                contest
+            })
+            .filter(move |contest| filter.accepts(contest, name_regex.as_ref()))
+            .boxed();
+        tracing::info!("Filtered the dataset down to {} contests", filtered.len());
+        Ok(filtered)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_processing::Wrap;
+
+    fn to_dataset(contests: &[Contest]) -> ContestDataset {
+        let contests = contests.to_vec();
+        Wrap::from_closure(contests.len(), move |i| contests[i].clone()).boxed()
+    }
+
+    #[test]
+    fn test_contest_filter() {
+        let contests: Vec<Contest> = (0..6)
+            .map(|index| {
+                let mut contest = Contest::new(index);
+                contest.name = format!("Round #{} (Div. {})", index, 1 + index % 2);
+                for i in 0..=index {
+                    contest.push_contestant(format!("player{}", i));
+                }
+                contest
+            })
+            .collect();
+        let apply = |filter: &ContestFilter| {
+            let filtered = filter.apply(to_dataset(&contests)).unwrap();
+            filtered.iter().map(|c| c.name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(apply(&ContestFilter::default()).len(), 6);
+        let by_time = ContestFilter {
+            min_time_seconds: Some(86_400),
+            max_time_seconds: Some(3 * 86_400),
+            ..Default::default()
+        };
+        assert_eq!(
+            apply(&by_time),
+            [
+                "Round #1 (Div. 2)",
+                "Round #2 (Div. 1)",
+                "Round #3 (Div. 2)"
+            ]
+        );
+        let by_name = ContestFilter {
+            name_pattern: Some(r"Div\. 1".to_owned()),
+            min_participants: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(apply(&by_name), ["Round #2 (Div. 1)", "Round #4 (Div. 1)"]);
+
+        // Categories are only assigned to names with one distinct match
+        let mut contests = contests;
+        contests[1].name = "Round #1 (Div. 1 + Div. 2)".to_owned();
+        contests[2].category = Some("special".to_owned());
+        let by_division = ContestFilter {
+            category_pattern: Some(r"Div\. [1-4]".to_owned()),
+            ..Default::default()
+        };
+        let categories: Vec<_> = by_division
+            .apply(to_dataset(&contests))
+            .unwrap()
+            .iter()
+            .map(|c| c.category)
+            .collect();
+        assert_eq!(categories[0].as_deref(), Some("Div. 1"));
+        assert_eq!(categories[1], None);
+        assert_eq!(categories[2].as_deref(), Some("special"));
+        assert_eq!(categories[3].as_deref(), Some("Div. 2"));
+
+        // Removing handles can make contests too small to keep
+        let by_handles = ContestFilter {
+            min_participants: Some(2),
+            removed_handles: HashSet::from(["player0".to_owned(), "player2".to_owned()]),
+            ..Default::default()
+        };
+        assert!(!by_handles.keeps_all());
+        let handles_only = ContestFilter {
+            min_participants: None,
+            ..by_handles.clone()
+        };
+        assert!(handles_only.keeps_all() && !handles_only.is_trivial());
+        let filtered = by_handles.apply(to_dataset(&contests)).unwrap();
+        assert_eq!(filtered.len(), 3);
+        let last = filtered.get(2);
+        assert_eq!(last.standings[0], ("player1".to_owned(), 0, 0));
+        assert_eq!(last.standings[3], ("player5".to_owned(), 3, 3));
+    }
+}
@@ -1,13 +1,23 @@
+mod archive;
 mod cf_api;
 mod ctf_api;
 mod dataset;
+mod filter;
+mod validation;
 
+use crate::systems::Rating;
+pub use archive::{ARCHIVE_EXTENSION, ArchiveDataset, get_dataset_from_archive, write_archive};
 pub use cf_api::fetch_cf_contest_ids;
-pub use dataset::{CachedDataset, ClosureDataset, Dataset, Wrap, get_dataset_from_disk};
+pub use dataset::{
+    CachedDataset, ClosureDataset, Dataset, MergedDataset, Wrap, get_dataset_from_disk,
+};
+pub use filter::ContestFilter;
 use rand::seq::SliceRandom;
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+pub use validation::{Violation, validate_dataset};
 
 pub const CURRENT_YEAR: usize = 2022;
 
@@ -35,6 +45,9 @@ pub struct ContestRatingParams {
     /// Maximum performance this contest is intended to measure, default is infinity.
     #[serde(default = "f64_max", skip_serializing_if = "is_f64_max")]
     pub perf_ceiling: f64,
+    /// Initial rating of players who debut in this contest, overriding the global default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub newcomer_prior: Option<Rating>,
 }
 
 impl Default for ContestRatingParams {
@@ -42,6 +55,7 @@ impl Default for ContestRatingParams {
         Self {
             weight: one(),
             perf_ceiling: f64_max(),
+            newcomer_prior: None,
         }
     }
 }
@@ -60,6 +74,22 @@ pub struct Contest {
     pub time_seconds: u64,
     /// The list of standings, containing a name and the enclosing range of ties.
     pub standings: Vec<(String, usize, usize)>,
+    /// Maps the name of each team in the standings to the handles of its members.
+    /// Standings entries without a mapping here are individual players.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub teams: HashMap<String, Vec<String>>,
+    /// Optional numerical score of each standings entry, higher being better.
+    /// Systems that support it may use the margins between scores as extra evidence.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub scores: HashMap<String, f64>,
+    /// Optional category, such as a division or style. Individual players are rated in it by
+    /// their general skill plus an offset that's specific to the category.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Names of standings entries who count as opponents, but whose own ratings don't change,
+    /// such as participants who are out of competition or above a division's cap.
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    pub unrated: HashSet<String>,
 }
 
 impl Contest {
@@ -71,6 +101,10 @@ impl Contest {
             rating_params: Default::default(),
             time_seconds: index as u64 * 86_400,
             standings: vec![],
+            teams: HashMap::new(),
+            scores: HashMap::new(),
+            category: None,
+            unrated: HashSet::new(),
         }
     }
 
@@ -94,10 +128,37 @@ impl Contest {
         Some(contestant)
     }
 
+    /// Remove the given handles from the standings, as well as from any teams. Teams left
+    /// without members are removed entirely.
+    pub fn remove_handles(&mut self, handles: &HashSet<String>) {
+        for members in self.teams.values_mut() {
+            members.retain(|handle| !handles.contains(handle));
+        }
+        let teams = &self.teams;
+        self.standings.retain(|(name, _, _)| match teams.get(name) {
+            Some(members) => !members.is_empty(),
+            None => !handles.contains(name),
+        });
+        self.fix_lo_hi();
+    }
+
+    /// If the contest has no category, takes the text that `pattern` matches in its name.
+    /// Names with several distinct matches are left without a category.
+    pub fn categorize_by_name(&mut self, pattern: &regex::Regex) {
+        if self.category.is_none() {
+            let mut matches = pattern.find_iter(&self.name).map(|m| m.as_str());
+            if let Some(first) = matches.next()
+                && matches.all(|m| m == first)
+            {
+                self.category = Some(first.to_owned());
+            }
+        }
+    }
+
     /// Assuming `self.standings` is a subset of a valid standings list,
     /// corrects the `lo` and `hi` values to make the new list valid
     fn fix_lo_hi(&mut self) {
-        self.standings.sort_unstable_by_key(|(_, lo, _)| *lo);
+        self.standings.sort_by_key(|(_, lo, _)| *lo);
         let len = self.standings.len();
         let mut lo = 0;
         while lo < len {
@@ -120,6 +181,10 @@ impl Contest {
             rating_params: self.rating_params,
             time_seconds: self.time_seconds,
             standings,
+            teams: self.teams.clone(),
+            scores: self.scores.clone(),
+            category: self.category.clone(),
+            unrated: self.unrated.clone(),
         };
         contest.fix_lo_hi();
         contest
@@ -143,6 +208,29 @@ impl Contest {
         let place = self.standings.len();
         self.standings.push((handle.into(), place, place));
     }
+
+    /// Add a team with the given name and member handles in last place.
+    pub fn push_team(&mut self, name: impl Into<String>, members: Vec<String>) {
+        let name = name.into();
+        self.teams.insert(name.clone(), members);
+        self.push_contestant(name);
+    }
+
+    /// Add an unrated contestant with the given handle in last place.
+    pub fn push_unrated(&mut self, handle: impl Into<String>) {
+        let handle = handle.into();
+        self.unrated.insert(handle.clone());
+        self.push_contestant(handle);
+    }
+
+    /// Returns the handles of the players represented by a standings entry:
+    /// the members if it's a team, or else the entry itself.
+    pub fn members<'a>(&'a self, name: &'a String) -> &'a [String] {
+        match self.teams.get(name) {
+            Some(members) => members,
+            None => std::slice::from_ref(name),
+        }
+    }
 }
 
 /// Compressed summary of a contest
@@ -257,9 +345,33 @@ pub fn get_dataset_from_ctftime_api() -> Wrap<impl Dataset<Item = Contest>> {
 pub type BoxedDataset<T> = Box<dyn Dataset<Item = T> + Send + Sync>;
 pub type ContestDataset = Wrap<BoxedDataset<Contest>>;
 
+/// Merges contest datasets into a single timeline, ordered by `time_seconds`. Its provenance
+/// maps each merged contest index back to a source and an index within it.
+pub fn merge_by_time<D: Dataset<Item = Contest>>(sources: Vec<D>) -> MergedDataset<D> {
+    MergedDataset::by_key(sources, |contest| contest.time_seconds)
+}
+
+/// Like `get_dataset_by_name()`, but keeping the merged dataset's provenance, where each of the
+/// names joined by '+' is a source. Merging reads every contest once, so this avoids reading
+/// them again to recover the provenance.
+pub fn get_merged_dataset_by_name(
+    dataset_name: &str,
+) -> Result<MergedDataset<ContestDataset>, String> {
+    let sources = dataset_name
+        .split('+')
+        .map(get_dataset_by_name)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(merge_by_time(sources))
+}
+
 /// Helper function to get any named dataset.
+/// Names joined by '+', such as "codeforces+internal", refer to a time-ordered merge of the
+/// named datasets. To map its indices back to the sources, use `get_merged_dataset_by_name()`.
 // TODO: actually throw errors when the directory is not found.
 pub fn get_dataset_by_name(dataset_name: &str) -> Result<ContestDataset, String> {
+    if dataset_name.contains('+') {
+        return Ok(get_merged_dataset_by_name(dataset_name)?.wrap().boxed());
+    }
     let dataset_dir = format!("../cache/{}", dataset_name);
     let dataset = if dataset_name == "codeforces" {
         // Rate-limit API calls so we don't burden Codeforces
@@ -269,6 +381,8 @@ pub fn get_dataset_by_name(dataset_name: &str) -> Result<ContestDataset, String>
             .boxed()
     //} else if dataset_name == "ctf" {
     //    get_dataset_from_ctftime_api().cached(dataset_dir).boxed()
+    } else if Path::new(&dataset_dir).extension() == Some(ARCHIVE_EXTENSION.as_ref()) {
+        get_dataset_from_archive(dataset_dir).boxed()
     } else {
         get_dataset_from_disk(dataset_dir).boxed()
     };
@@ -0,0 +1,318 @@
+use super::{Contest, Dataset};
+use std::collections::HashSet;
+use std::fmt;
+
+/// A way in which a contest, or a sequence of contests, is malformed.
+/// Positions are indices into the standings.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    /// A group of tied entries starts after the rank at which it should,
+    /// leaving some ranks unoccupied
+    RankGap { position: usize, lo: usize },
+    /// A group of tied entries starts before the rank at which it should,
+    /// sharing ranks with the preceding group
+    RankOverlap { position: usize, lo: usize },
+    /// The number of entries sharing the range of ranks lo..=hi doesn't match its size
+    TieSize {
+        position: usize,
+        lo: usize,
+        hi: usize,
+        count: usize,
+    },
+    /// A player appears in more than one standings entry, directly or as a team member
+    DuplicateHandle { position: usize, handle: String },
+    /// A team in the standings has no members
+    EmptyTeam { position: usize, team: String },
+    /// A name in the contest's `field`, which is one of unrated, teams or scores,
+    /// doesn't match any standings entry
+    UnlistedName { field: &'static str, name: String },
+    /// The contest ends earlier than its predecessor in the dataset
+    TimeBackwards { previous: u64, current: u64 },
+}
+
+impl Violation {
+    /// Whether `Contest::repair()` corrects this violation
+    pub fn is_repairable(&self) -> bool {
+        !matches!(self, Self::TimeBackwards { .. })
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RankGap { position, lo } => {
+                write!(f, "standings[{}] skips ahead to rank {}", position, lo)
+            }
+            Self::RankOverlap { position, lo } => {
+                write!(f, "standings[{}] reuses rank {}", position, lo)
+            }
+            Self::TieSize {
+                position,
+                lo,
+                hi,
+                count,
+            } => write!(
+                f,
+                "standings[{}] starts {} entries tied over ranks {}..={}",
+                position, count, lo, hi
+            ),
+            Self::DuplicateHandle { position, handle } => {
+                write!(f, "standings[{}] repeats the handle {}", position, handle)
+            }
+            Self::EmptyTeam { position, team } => {
+                write!(
+                    f,
+                    "standings[{}] is the team {} with no members",
+                    position, team
+                )
+            }
+            Self::UnlistedName { field, name } => {
+                write!(f, "{} lists {}, which isn't in the standings", field, name)
+            }
+            Self::TimeBackwards { previous, current } => write!(
+                f,
+                "time_seconds goes backwards from {} to {}",
+                previous, current
+            ),
+        }
+    }
+}
+
+impl Contest {
+    /// Lists every way in which this contest's standings are malformed
+    pub fn violations(&self) -> Vec<Violation> {
+        let mut violations = vec![];
+
+        // Consecutive entries with equal ranges form a group of ties. The groups should tile
+        // the ranks 0..len() in order, each having as many entries as ranks.
+        let mut position = 0;
+        let mut expected_lo = 0;
+        for group in self.standings.chunk_by(|a, b| (a.1, a.2) == (b.1, b.2)) {
+            let (_, lo, hi) = group[0];
+            if lo > expected_lo {
+                violations.push(Violation::RankGap { position, lo });
+            } else if lo < expected_lo {
+                violations.push(Violation::RankOverlap { position, lo });
+            }
+            if hi < lo || hi - lo + 1 != group.len() {
+                violations.push(Violation::TieSize {
+                    position,
+                    lo,
+                    hi,
+                    count: group.len(),
+                });
+            }
+            position += group.len();
+            expected_lo = hi + 1;
+        }
+
+        let mut seen = HashSet::new();
+        for (position, (name, _, _)) in self.standings.iter().enumerate() {
+            if self.teams.get(name).is_some_and(Vec::is_empty) {
+                violations.push(Violation::EmptyTeam {
+                    position,
+                    team: name.clone(),
+                });
+            }
+            for handle in self.members(name) {
+                if !seen.insert(handle) {
+                    violations.push(Violation::DuplicateHandle {
+                        position,
+                        handle: handle.clone(),
+                    });
+                }
+            }
+        }
+
+        // Names are sorted so that the violations come in a deterministic order
+        let listed: HashSet<&String> = self.standings.iter().map(|(name, _, _)| name).collect();
+        let fields: [(&'static str, Vec<&String>); 3] = [
+            ("unrated", self.unrated.iter().collect()),
+            ("teams", self.teams.keys().collect()),
+            ("scores", self.scores.keys().collect()),
+        ];
+        for (field, mut names) in fields {
+            names.retain(|name| !listed.contains(name));
+            names.sort();
+            violations.extend(names.into_iter().map(|name| Violation::UnlistedName {
+                field,
+                name: name.clone(),
+            }));
+        }
+        violations
+    }
+
+    /// Corrects all repairable violations: empty teams, and any entry sharing a handle with a
+    /// better-placed entry, are removed, as are names that match no remaining entry. Then the
+    /// remaining entries are ordered by lo and renumbered, treating entries with equal lo as ties.
+    pub fn repair(&mut self) {
+        // A stable sort keeps tied entries in order, so that the earliest of them wins
+        self.standings.sort_by_key(|(_, lo, _)| *lo);
+        let mut seen = HashSet::new();
+        let teams = &self.teams;
+        self.standings.retain(|(name, _, _)| {
+            let members = teams
+                .get(name)
+                .map_or(std::slice::from_ref(name), Vec::as_slice);
+            let is_valid = !members.is_empty() && members.iter().all(|h| !seen.contains(h));
+            if is_valid {
+                seen.extend(members.iter().cloned());
+            }
+            is_valid
+        });
+
+        let listed: HashSet<String> = self.standings.iter().map(|st| st.0.clone()).collect();
+        self.unrated.retain(|name| listed.contains(name));
+        self.teams.retain(|name, _| listed.contains(name));
+        self.scores.retain(|name, _| listed.contains(name));
+        self.fix_lo_hi();
+    }
+}
+
+/// Lists every violation in a dataset, along with the index of the contest where it occurs
+pub fn validate_dataset<D: Dataset<Item = Contest> + ?Sized>(
+    dataset: &D,
+) -> Vec<(usize, Violation)> {
+    let mut violations = vec![];
+    let mut previous_time = 0;
+    for index in 0..dataset.len() {
+        let contest = dataset.get(index);
+        violations.extend(contest.violations().into_iter().map(|v| (index, v)));
+        if contest.time_seconds < previous_time {
+            violations.push((
+                index,
+                Violation::TimeBackwards {
+                    previous: previous_time,
+                    current: contest.time_seconds,
+                },
+            ));
+        }
+        previous_time = contest.time_seconds;
+    }
+    violations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::systems::{EloMMR, PlayersByName, simulate_contest};
+
+    fn contest_with(standings: &[(&str, usize, usize)]) -> Contest {
+        let mut contest = Contest::new(0);
+        contest.standings = standings
+            .iter()
+            .map(|&(name, lo, hi)| (name.to_owned(), lo, hi))
+            .collect();
+        contest
+    }
+
+    #[test]
+    fn test_violations() {
+        let valid = contest_with(&[("a", 0, 0), ("b", 1, 2), ("c", 1, 2), ("d", 3, 3)]);
+        assert_eq!(valid.violations(), vec![]);
+
+        let mut invalid = contest_with(&[("a", 0, 0), ("b", 2, 2), ("c", 2, 3), ("b", 3, 3)]);
+        assert_eq!(
+            invalid.violations(),
+            vec![
+                Violation::RankGap { position: 1, lo: 2 },
+                Violation::RankOverlap { position: 2, lo: 2 },
+                Violation::TieSize {
+                    position: 2,
+                    lo: 2,
+                    hi: 3,
+                    count: 1
+                },
+                Violation::RankOverlap { position: 3, lo: 3 },
+                Violation::DuplicateHandle {
+                    position: 3,
+                    handle: "b".to_owned()
+                },
+            ]
+        );
+
+        invalid.repair();
+        assert_eq!(invalid.violations(), vec![]);
+        let names: Vec<_> = invalid.standings.iter().map(|st| st.0.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+
+        // The better-placed duplicate is kept, even if it's listed later
+        let mut unsorted = contest_with(&[("b", 2, 2), ("a", 1, 1), ("b", 0, 0)]);
+        unsorted.repair();
+        let names: Vec<_> = unsorted.standings.iter().map(|st| st.0.as_str()).collect();
+        assert_eq!(names, vec!["b", "a"]);
+
+        // Team members count as handles too
+        let mut teams = contest_with(&[]);
+        teams.push_team("team", vec!["a".to_owned(), "b".to_owned()]);
+        teams.push_contestant("b");
+        assert_eq!(teams.violations().len(), 1);
+        teams.repair();
+        assert_eq!(teams.standings.len(), 1);
+
+        // Teams need members, and other fields may only refer to names in the standings
+        let mut names = contest_with(&[("a", 0, 0)]);
+        names.push_team("empty", vec![]);
+        names.unrated.insert("ghost".to_owned());
+        names.scores.insert("a".to_owned(), 5.);
+        names.scores.insert("nobody".to_owned(), 1.);
+        assert_eq!(
+            names.violations(),
+            vec![
+                Violation::EmptyTeam {
+                    position: 1,
+                    team: "empty".to_owned()
+                },
+                Violation::UnlistedName {
+                    field: "unrated",
+                    name: "ghost".to_owned()
+                },
+                Violation::UnlistedName {
+                    field: "scores",
+                    name: "nobody".to_owned()
+                },
+            ]
+        );
+        names.repair();
+        assert_eq!(names.violations(), vec![]);
+        assert_eq!(names.standings, vec![("a".to_owned(), 0, 0)]);
+        assert!(names.teams.is_empty() && names.unrated.is_empty());
+        assert_eq!(names.scores.len(), 1);
+
+        let mut late = contest_with(&[]);
+        late.time_seconds = 5;
+        let dataset = [late, contest_with(&[])];
+        assert_eq!(
+            validate_dataset(dataset.as_slice()),
+            vec![(
+                1,
+                Violation::TimeBackwards {
+                    previous: 5,
+                    current: 0
+                }
+            )]
+        );
+    }
+    #[test]
+    fn test_repaired_contests_can_be_rated() {
+        let mut contest = contest_with(&[("a", 0, 0)]);
+        contest.push_team("empty", vec![]);
+        contest.push_team("pair", vec!["b".to_owned(), "c".to_owned()]);
+        contest.push_unrated("d");
+        let simulate = |contest: &Contest| {
+            let mut players = PlayersByName::new();
+            simulate_contest(&mut players, contest, &EloMMR::default(), 1500., 350., 0);
+            players
+        };
+
+        // Rating the contest as it is trips over the empty team
+        let unrepaired = contest.clone();
+        assert!(std::panic::catch_unwind(|| simulate(&unrepaired)).is_err());
+
+        contest.repair();
+        assert_eq!(contest.violations(), vec![]);
+        let players = simulate(&contest);
+        assert_eq!(players.len(), 3);
+        assert!(players["a"].borrow().approx_posterior.mu > 1500.);
+    }
+}
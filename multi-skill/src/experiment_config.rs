@@ -1,14 +1,18 @@
-use crate::data_processing::{ContestDataset, Dataset, get_dataset_by_name};
+use crate::data_processing::{
+    Contest, ContestDataset, ContestFilter, Dataset, get_dataset_by_name,
+};
 use crate::systems::{
-    BAR, CodeforcesSys, EloMMR, EloMMRVariant, EndureElo, Glicko, PlayersByName, RatingSystem,
-    SimpleEloMMR, TopcoderSys, TrueSkillSPb, simulate_contest,
+    BAR, CodeforcesSys, EloMMR, EloMMRVariant, EndureElo, Glicko, Glicko2, InactivityDecay,
+    PlackettLuce, PlayersByName, Rating, RatingSystem, SimpleEloMMR, SubsampleMode,
+    ThurstoneMosteller, TopcoderSys, TrueSkillSPb, simulate_contest_seeded,
 };
 
 use crate::data_processing::{read_json, write_json};
 use crate::metrics::{PerformanceReport, compute_metrics_custom};
+use crate::numerical::SolverStats;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
 
@@ -18,9 +22,18 @@ pub enum SystemParams {
     Glicko {
         params: Vec<f64>,
     },
+    Glicko2 {
+        params: Vec<f64>,
+    },
     Bar {
         params: Vec<f64>,
     },
+    BarPl {
+        params: Vec<f64>,
+    },
+    BarTm {
+        params: Vec<f64>,
+    },
     Endure {
         params: Vec<f64>,
     },
@@ -35,9 +48,29 @@ pub enum SystemParams {
     },
     Mmx {
         params: Vec<f64>,
+        // Whether to use the GaussianExact variant
+        #[serde(default)]
+        exact: bool,
+        #[serde(default)]
+        subsample_mode: SubsampleMode,
+        #[serde(default)]
+        margin_sig: Option<f64>,
+        #[serde(default)]
+        inactivity_decay: Option<InactivityDecay>,
     },
     Mmr {
         params: Vec<f64>,
+        #[serde(default)]
+        subsample_mode: SubsampleMode,
+        // Defaults to the subsample size, to which it was tied before it could be configured
+        #[serde(default)]
+        history_len: Option<usize>,
+        #[serde(default)]
+        table_tolerance: Option<f64>,
+        #[serde(default)]
+        margin_sig: Option<f64>,
+        #[serde(default)]
+        inactivity_decay: Option<InactivityDecay>,
     },
     // Experimental support for named config params
     // TODO: find nice ways to use defaults and interface with constructor
@@ -49,9 +82,72 @@ pub enum SystemParams {
         split_ties: bool,
         history_len: usize,
         transfer_speed: f64,
+        #[serde(default)]
+        inactivity_decay: Option<InactivityDecay>,
     },
 }
 
+impl SystemParams {
+    /// The Elo-MMR system that these params describe, if they describe one
+    pub fn elo_mmr(&self) -> Option<EloMMR> {
+        match *self {
+            Self::Mmx {
+                ref params,
+                exact,
+                subsample_mode,
+                margin_sig,
+                inactivity_decay,
+            } => Some(EloMMR {
+                weight_limit: params[0],
+                noob_delay: vec![], // TODO: add this to the config spec
+                sig_limit: params[1],
+                drift_per_day: 0.,
+                split_ties: params[2] > 0.,
+                subsample_size: params[3] as usize,
+                subsample_bucket: params[4],
+                subsample_mode,
+                history_len: usize::MAX,
+                variant: if exact {
+                    EloMMRVariant::GaussianExact
+                } else {
+                    EloMMRVariant::Gaussian
+                },
+                table_tolerance: None,
+                margin_sig,
+                inactivity_decay,
+            }),
+            Self::Mmr {
+                ref params,
+                subsample_mode,
+                history_len,
+                table_tolerance,
+                margin_sig,
+                inactivity_decay,
+            } => {
+                let subsample_size = params[3] as usize;
+                let history_len = history_len.unwrap_or(subsample_size);
+                assert!(history_len > 0, "history_len must be at least 1");
+                Some(EloMMR {
+                    weight_limit: params[0],
+                    noob_delay: vec![], // TODO: add this to the config spec
+                    sig_limit: params[1],
+                    drift_per_day: 0.,
+                    split_ties: params[2] > 0.,
+                    subsample_size,
+                    subsample_bucket: params[4],
+                    subsample_mode,
+                    history_len,
+                    variant: EloMMRVariant::Logistic(params[5]),
+                    table_tolerance,
+                    margin_sig,
+                    inactivity_decay,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
 fn usize_zero() -> usize {
     0
 }
@@ -80,8 +176,18 @@ pub struct ExperimentConfig {
     pub sig_noob: f64,
     pub system: SystemParams,
     pub contest_source: String,
+    /// Selects which contests of the source to rate, before skip_contests and max_contests apply
+    #[serde(default)]
+    pub filter: ContestFilter,
     pub load_checkpoint: Option<String>,
     pub save_checkpoint: Option<String>,
+    /// If set, skip_contests is replaced by the number of contests that the loaded checkpoint
+    /// has already rated, so that only newly added contests are rated. A checkpoint file that
+    /// doesn't exist yet counts as an empty state.
+    #[serde(default)]
+    pub incremental: bool,
+    /// A JSON file mapping handles to initial ratings, for seeding first-time players
+    pub seed_file: Option<String>,
 }
 
 impl ExperimentConfig {
@@ -92,6 +198,34 @@ impl ExperimentConfig {
     }
 }
 
+/// The ratings of every player after some prefix of a contest source
+#[derive(Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The number of contests from the start of the source that these ratings reflect,
+    /// if known; older checkpoints didn't record it
+    #[serde(default)]
+    pub contests_processed: Option<usize>,
+    pub players: PlayersByName,
+}
+
+// Checkpoints used to contain only the players
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CheckpointFile {
+    Current(Checkpoint),
+    Legacy(PlayersByName),
+}
+
+pub fn read_checkpoint(path: impl AsRef<Path>) -> Result<Checkpoint, String> {
+    Ok(match read_json(path)? {
+        CheckpointFile::Current(checkpoint) => checkpoint,
+        CheckpointFile::Legacy(players) => Checkpoint {
+            contests_processed: None,
+            players,
+        },
+    })
+}
+
 pub struct Experiment {
     pub mu_noob: f64,
     pub sig_noob: f64,
@@ -99,30 +233,108 @@ pub struct Experiment {
     pub system: Box<dyn RatingSystem + Send>,
     pub dataset: ContestDataset,
     pub loaded_state: PlayersByName,
+    // the number of contests preceding the dataset in its source, by which its contests are
+    // numbered in the players' event histories
+    pub contests_before: usize,
+    // the number of those contests that loaded_state reflects, or None if some of them were
+    // skipped without being rated
+    pub contests_processed: Option<usize>,
     pub save_checkpoint: Option<String>,
+    // initial ratings for first-time players, keyed by handle
+    pub seeds: HashMap<String, Rating>,
 }
 
 impl Experiment {
     pub fn from_config(config: ExperimentConfig) -> Self {
         tracing::info!("Loading rating system:\n{:?}", config);
+        let checkpoint = match &config.load_checkpoint {
+            Some(filename) if config.incremental && !Path::new(filename).exists() => {
+                tracing::info!("No checkpoint at {}; starting from scratch", filename);
+                Checkpoint {
+                    contests_processed: Some(0),
+                    players: HashMap::new(),
+                }
+            }
+            Some(filename) => read_checkpoint(filename).expect("Failed to read checkpoint"),
+            None => Checkpoint {
+                contests_processed: Some(0),
+                players: HashMap::new(),
+            },
+        };
+        let skip_contests = if config.incremental {
+            // The checkpoint counts contests after filtering, so dropping contests would make
+            // the count depend on the filter having stayed the same between runs
+            assert!(
+                config.filter.keeps_all(),
+                "Incremental rating can't be combined with a filter that drops contests"
+            );
+            checkpoint
+                .contests_processed
+                .expect("Incremental rating needs a checkpoint that records its contests")
+        } else {
+            // A checkpoint that records its contests must continue from where it left off
+            if let (Some(_), Some(processed)) =
+                (&config.load_checkpoint, checkpoint.contests_processed)
+            {
+                assert_eq!(
+                    processed, config.skip_contests,
+                    "The checkpoint has rated {} contests, but skip_contests is {}",
+                    processed, config.skip_contests
+                );
+            }
+            config.skip_contests
+        };
+
         let dataset_full = get_dataset_by_name(&config.contest_source).unwrap();
+        let dataset_full = config
+            .filter
+            .apply(dataset_full)
+            .expect("Invalid contest filter");
+        assert!(
+            skip_contests <= dataset_full.len(),
+            "Skipping {} contests, but {} has only {}",
+            skip_contests,
+            config.contest_source,
+            dataset_full.len()
+        );
         let dataset_end = dataset_full
             .len()
-            .min(config.skip_contests + config.max_contests);
-        let dataset = dataset_full
-            .subrange(config.skip_contests..dataset_end)
-            .boxed();
+            .min(skip_contests.saturating_add(config.max_contests));
+        let dataset = dataset_full.subrange(skip_contests..dataset_end).boxed();
+        if config.incremental {
+            tracing::info!(
+                "Rating {} new contests after the first {}",
+                dataset.len(),
+                skip_contests
+            );
+        }
 
         let system: Box<dyn RatingSystem + Send> = match config.system {
             SystemParams::Glicko { params } => Box::new(Glicko {
                 beta: params[0],
                 sig_drift: params[1],
             }),
+            SystemParams::Glicko2 { params } => Box::new(Glicko2 {
+                beta: params[0],
+                tau: params[1],
+                vol_noob: params[2],
+            }),
             SystemParams::Bar { params } => Box::new(BAR {
                 beta: params[0],
                 sig_drift: params[1],
                 kappa: 1e-4,
             }),
+            SystemParams::BarPl { params } => Box::new(PlackettLuce {
+                beta: params[0],
+                sig_drift: params[1],
+                kappa: 1e-4,
+            }),
+            SystemParams::BarTm { params } => Box::new(ThurstoneMosteller {
+                beta: params[0],
+                sig_drift: params[1],
+                kappa: 1e-4,
+                eps: params[2],
+            }),
             SystemParams::Endure { params } => Box::new(EndureElo {
                 beta: params[0],
                 sig_drift: params[1],
@@ -141,26 +353,9 @@ impl Experiment {
                 convergence_eps: params[2],
                 sig_drift: params[3],
             }),
-            SystemParams::Mmx { params } => Box::new(EloMMR {
-                weight_limit: params[0],
-                noob_delay: vec![], // TODO: add this to the config spec
-                sig_limit: params[1],
-                drift_per_day: 0.,
-                split_ties: params[2] > 0.,
-                subsample_size: params[3] as usize,
-                subsample_bucket: params[4],
-                variant: EloMMRVariant::Gaussian,
-            }),
-            SystemParams::Mmr { params } => Box::new(EloMMR {
-                weight_limit: params[0],
-                noob_delay: vec![], // TODO: add this to the config spec
-                sig_limit: params[1],
-                drift_per_day: 0.,
-                split_ties: params[2] > 0.,
-                subsample_size: params[3] as usize,
-                subsample_bucket: params[4],
-                variant: EloMMRVariant::Logistic(params[5]),
-            }),
+            ref elo_mmr @ (SystemParams::Mmx { .. } | SystemParams::Mmr { .. }) => {
+                Box::new(elo_mmr.elo_mmr().unwrap())
+            }
             SystemParams::MmrSimple {
                 weight_limit,
                 noob_delay,
@@ -169,19 +364,29 @@ impl Experiment {
                 split_ties,
                 history_len,
                 transfer_speed,
-            } => Box::new(SimpleEloMMR {
-                weight_limit,
-                noob_delay,
-                sig_limit,
-                drift_per_day,
-                split_ties,
-                history_len,
-                transfer_speed,
-            }),
+                inactivity_decay,
+            } => {
+                assert!(history_len > 0, "history_len must be at least 1");
+                Box::new(SimpleEloMMR {
+                    weight_limit,
+                    noob_delay,
+                    sig_limit,
+                    drift_per_day,
+                    split_ties,
+                    history_len,
+                    transfer_speed,
+                    inactivity_decay,
+                })
+            }
         };
 
-        let loaded_state = match config.load_checkpoint {
-            Some(filename) => read_json(filename).expect("Failed to read checkpoint"),
+        // Skipped contests count as rated only if they're the ones that the checkpoint reflects;
+        // a legacy checkpoint, which doesn't record its count, is trusted to match skip_contests
+        let contests_processed =
+            (config.load_checkpoint.is_some() || skip_contests == 0).then_some(skip_contests);
+
+        let seeds = match config.seed_file {
+            Some(filename) => read_json(filename).expect("Failed to read seed file"),
             None => HashMap::new(),
         };
 
@@ -190,14 +395,35 @@ impl Experiment {
             sig_noob: config.sig_noob,
             system,
             dataset,
-            loaded_state,
+            loaded_state: checkpoint.players,
+            contests_before: skip_contests,
+            contests_processed,
             save_checkpoint: config.save_checkpoint,
+            seeds,
+        }
+    }
+
+    // Saves the players to the checkpoint file, if any, and gives them back
+    fn save(&self, players: PlayersByName) -> PlayersByName {
+        match &self.save_checkpoint {
+            Some(filename) => {
+                let checkpoint = Checkpoint {
+                    contests_processed: self
+                        .contests_processed
+                        .map(|processed| processed + self.dataset.len()),
+                    players,
+                };
+                write_json(&checkpoint, filename).expect("Failed to save checkpoint");
+                checkpoint.players
+            }
+            None => players,
         }
     }
 
     pub fn eval(&self, num_rounds_postpone_eval: usize) -> ExperimentResults {
         let mut players = self.loaded_state.clone();
-        let mut avg_perf = compute_metrics_custom(&mut players, &[]);
+        let mut avg_perf = compute_metrics_custom(&mut players, &Contest::new(0), &*self.system);
+        let mut solver_stats = Vec::with_capacity(self.dataset.len());
 
         // Run the contest histories and measure
         let now = std::time::Instant::now();
@@ -205,7 +431,7 @@ impl Experiment {
             // Evaluate the non-training set; predictions should not use the contest
             // that they're predicting, so this step precedes simulation
             if index >= num_rounds_postpone_eval {
-                avg_perf += compute_metrics_custom(&mut players, &contest.standings);
+                avg_perf += compute_metrics_custom(&mut players, &contest, &*self.system);
             }
 
             tracing::debug!(
@@ -217,25 +443,25 @@ impl Experiment {
             );
 
             // Now run the actual rating update
-            simulate_contest(
+            let stats = simulate_contest_seeded(
                 &mut players,
                 &contest,
                 &*self.system,
                 self.mu_noob,
                 self.sig_noob,
-                index,
+                self.contests_before + index,
+                &|handle| self.seeds.get(handle).copied(),
             );
+            solver_stats.push(stats);
         }
         let secs_elapsed = now.elapsed().as_nanos() as f64 * 1e-9;
 
-        if let Some(filename) = &self.save_checkpoint {
-            write_json(&players, filename).expect("Failed to save checkpoint");
-        }
-
+        let players = self.save(players);
         ExperimentResults {
             players,
             avg_perf,
             secs_elapsed,
+            solver_stats,
         }
     }
 
@@ -246,7 +472,8 @@ impl Experiment {
         rng_seed: u64,
     ) -> ExperimentResults {
         let mut players = self.loaded_state.clone();
-        let mut avg_perf = compute_metrics_custom(&mut players, &[]);
+        let mut avg_perf = compute_metrics_custom(&mut players, &Contest::new(0), &*self.system);
+        let mut solver_stats = Vec::with_capacity(self.dataset.len());
 
         let mut rng = StdRng::seed_from_u64(rng_seed);
         let now = std::time::Instant::now();
@@ -260,34 +487,35 @@ impl Experiment {
                 split_contests.size_hint().0
             );
 
+            let mut contest_stats = SolverStats::default();
             for subcontest in split_contests {
                 // Evaluate the non-training set; predictions should not use the contest
                 // that they're predicting, so this step precedes simulation
                 if index >= num_rounds_postpone_eval {
-                    avg_perf += compute_metrics_custom(&mut players, &subcontest.standings);
+                    avg_perf += compute_metrics_custom(&mut players, &subcontest, &*self.system);
                 }
 
                 // Now run the actual rating update
-                simulate_contest(
+                contest_stats += simulate_contest_seeded(
                     &mut players,
                     &subcontest,
                     &*self.system,
                     self.mu_noob,
                     self.sig_noob,
-                    index,
+                    self.contests_before + index,
+                    &|handle| self.seeds.get(handle).copied(),
                 );
             }
+            solver_stats.push(contest_stats);
         }
         let secs_elapsed = now.elapsed().as_nanos() as f64 * 1e-9;
 
-        if let Some(filename) = &self.save_checkpoint {
-            write_json(&players, filename).expect("Failed to save checkpoint");
-        }
-
+        let players = self.save(players);
         ExperimentResults {
             players,
             avg_perf,
             secs_elapsed,
+            solver_stats,
         }
     }
 }
@@ -296,4 +524,123 @@ pub struct ExperimentResults {
     pub players: PlayersByName,
     pub avg_perf: PerformanceReport,
     pub secs_elapsed: f64,
+    // Convergence statistics of the rating system's numerical solvers, for each contest
+    pub solver_stats: Vec<SolverStats>,
+}
+
+impl ExperimentResults {
+    // Counts the contests in which some numerical solver may have failed to converge
+    pub fn contests_with_solver_failures(&self) -> usize {
+        self.solver_stats
+            .iter()
+            .filter(|stats| stats.failures > 0)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_processing::Wrap;
+
+    #[test]
+    fn test_incremental_checkpoint() {
+        let id = std::process::id();
+        let contest_source = format!("temp_incremental_contests_{}", id);
+        let cache_dir = format!("../cache/{}", contest_source);
+        let checkpoint_file = std::env::temp_dir().join(format!("incremental_checkpoint_{}", id));
+        let checkpoint_name = checkpoint_file.to_str().unwrap().to_owned();
+        let contests: Vec<Contest> = (0..5)
+            .map(|index| {
+                let mut contest = Contest::new(index);
+                for i in 0..8 {
+                    contest.push_contestant(format!("player{}", (i * 3 + index) % 10));
+                }
+                contest
+            })
+            .collect();
+        let contests_clone = contests.clone();
+        Wrap::from_closure(contests.len(), move |i| contests_clone[i].clone())
+            .cached(&cache_dir)
+            .iter()
+            .count();
+
+        let config = |incremental, skip_contests, max_contests, checkpoint: Option<&String>| {
+            ExperimentConfig {
+                skip_contests,
+                max_contests,
+                mu_noob: 1500.,
+                sig_noob: 350.,
+                system: SystemParams::Glicko {
+                    params: vec![80., 35.],
+                },
+                contest_source: contest_source.clone(),
+                filter: ContestFilter::default(),
+                load_checkpoint: checkpoint.cloned(),
+                save_checkpoint: checkpoint.cloned(),
+                incremental,
+                seed_file: None,
+            }
+        };
+        let rate = |config| Experiment::from_config(config).eval(0).players;
+
+        // Rate the first three contests, starting from a checkpoint that doesn't exist yet,
+        // then append the remaining two
+        rate(config(true, 0, 3, Some(&checkpoint_name)));
+        let checkpoint = read_checkpoint(&checkpoint_file).unwrap();
+        assert_eq!(checkpoint.contests_processed, Some(3));
+        // Without incremental, the checkpoint must still be resumed after the contests it rated
+        let mismatched = std::panic::catch_unwind(|| {
+            Experiment::from_config(config(false, 1, usize::MAX, Some(&checkpoint_name)))
+        });
+        assert!(mismatched.is_err());
+        let incremental = rate(config(true, 0, usize::MAX, Some(&checkpoint_name)));
+        let checkpoint = read_checkpoint(&checkpoint_file).unwrap();
+        assert_eq!(checkpoint.contests_processed, Some(5));
+
+        // Checkpoints that contain only the players can still be read
+        write_json(&checkpoint.players, &checkpoint_file).unwrap();
+        let legacy = read_checkpoint(&checkpoint_file).unwrap();
+        assert_eq!(legacy.contests_processed, None);
+        assert_eq!(legacy.players.len(), incremental.len());
+        std::fs::remove_file(&checkpoint_file).unwrap();
+
+        // Contests that were skipped without a checkpoint don't count as rated, but events are
+        // still numbered by their position in the source
+        let experiment = Experiment::from_config(config(false, 2, usize::MAX, None));
+        assert_eq!(experiment.contests_before, 2);
+        assert_eq!(experiment.contests_processed, None);
+        let mut skipping = config(false, 2, usize::MAX, None);
+        skipping.save_checkpoint = Some(checkpoint_name.clone());
+        let skipped = rate(skipping);
+        let first_index = skipped
+            .values()
+            .flat_map(|player| player.borrow().event_history.clone())
+            .map(|event| event.contest_index)
+            .min();
+        assert_eq!(first_index, Some(2));
+        let checkpoint = read_checkpoint(&checkpoint_file).unwrap();
+        assert_eq!(checkpoint.contests_processed, None);
+        std::fs::remove_file(&checkpoint_file).unwrap();
+
+        let full = rate(config(false, 0, usize::MAX, None));
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+        assert_eq!(full.len(), incremental.len());
+        for (handle, player) in &full {
+            let (expected, actual) = (player.borrow(), incremental[handle].borrow());
+            let (expected_rating, actual_rating) =
+                (expected.approx_posterior, actual.approx_posterior);
+            assert!(
+                (expected_rating.mu - actual_rating.mu).abs() < 1e-6,
+                "{}",
+                handle
+            );
+            assert!(
+                (expected_rating.sig - actual_rating.sig).abs() < 1e-6,
+                "{}",
+                handle
+            );
+            assert_eq!(expected.event_history, actual.event_history, "{}", handle);
+        }
+    }
 }
@@ -2,5 +2,6 @@ pub mod data_processing;
 pub mod experiment_config;
 pub mod metrics;
 pub mod numerical;
+pub mod smoothing;
 pub mod summary;
 pub mod systems;
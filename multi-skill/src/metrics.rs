@@ -1,4 +1,5 @@
-use crate::systems::{PlayersByName, Rating, get_participant_ratings, outcome_free};
+use crate::data_processing::Contest;
+use crate::systems::{PlayersByName, Rating, RatingSystem, get_participant_ratings, outcome_free};
 use overload::overload;
 use std::fmt;
 use std::ops;
@@ -146,10 +147,11 @@ pub fn cross_entropy_metric(standings: &ParticipantRatings, scale: f64) -> Weigh
 // Meant to be modified manually to contain the desired metrics
 pub fn compute_metrics_custom(
     players: &mut PlayersByName,
-    contest_standings: &[(String, usize, usize)],
+    contest: &Contest,
+    system: &dyn RatingSystem,
 ) -> PerformanceReport {
-    let everyone = get_participant_ratings(players, contest_standings, 0);
-    let experienced = get_participant_ratings(players, contest_standings, 5);
+    let everyone = get_participant_ratings(players, contest, system, 0);
+    let experienced = get_participant_ratings(players, contest, system, 5);
     let top100 = top_k(&everyone, 100);
 
     let metrics_wt_sum = vec![
@@ -1,3 +1,6 @@
+use overload::overload;
+use std::ops;
+
 pub const TANH_MULTIPLIER: f64 = std::f64::consts::PI / 1.7320508075688772;
 
 #[allow(dead_code)]
@@ -74,10 +77,70 @@ pub fn solve_illinois((mut lo, mut hi): (f64, f64), f: impl Fn(f64) -> f64) -> f
     }
 }
 
-pub fn solve_newton((mut lo, mut hi): (f64, f64), f: impl Fn(f64) -> (f64, f64)) -> f64 {
+// Diagnostics from a single run of Newton's method
+#[derive(Clone, Copy, Debug)]
+pub struct NewtonResult {
+    pub root: f64,
+    pub iterations: usize,
+    pub residual: f64,
+    pub converged: bool,
+}
+
+impl NewtonResult {
+    // Returns the root, logging a warning if the solver may have failed to converge
+    pub fn root_or_warn(&self) -> f64 {
+        if !self.converged {
+            tracing::warn!(
+                "Possible failure to converge @ {}: s={}",
+                self.root,
+                self.residual
+            );
+        }
+        self.root
+    }
+}
+
+// Convergence statistics, aggregated over many runs of Newton's method
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SolverStats {
+    pub solves: usize,
+    pub failures: usize,
+    pub max_iterations: usize,
+    pub max_residual: f64,
+}
+
+impl SolverStats {
+    pub fn record(&mut self, result: &NewtonResult) {
+        self.solves += 1;
+        if !result.converged {
+            self.failures += 1;
+        }
+        self.max_iterations = self.max_iterations.max(result.iterations);
+        self.max_residual = self.max_residual.max(result.residual.abs());
+    }
+}
+
+overload!((a: &mut SolverStats) += (b: ?SolverStats) {
+    a.solves += b.solves;
+    a.failures += b.failures;
+    a.max_iterations = a.max_iterations.max(b.max_iterations);
+    a.max_residual = a.max_residual.max(b.max_residual);
+});
+
+pub fn solve_newton(bounds: (f64, f64), f: impl Fn(f64) -> (f64, f64)) -> f64 {
+    solve_newton_with_stats(bounds, f).root_or_warn()
+}
+
+// Same as solve_newton(), but reporting on convergence instead of logging failures
+pub fn solve_newton_with_stats(
+    (mut lo, mut hi): (f64, f64),
+    f: impl Fn(f64) -> (f64, f64),
+) -> NewtonResult {
     let mut guess = 0.5 * (lo + hi);
+    let mut iterations = 0;
     loop {
         let (sum, sum_prime) = f(guess);
+        iterations += 1;
         let extrapolate = guess - sum / sum_prime;
         if extrapolate < guess {
             hi = guess;
@@ -87,15 +150,87 @@ pub fn solve_newton((mut lo, mut hi): (f64, f64), f: impl Fn(f64) -> (f64, f64))
             guess = extrapolate.clamp(lo, lo + 0.75 * (hi - lo));
         }
         if lo >= guess || guess >= hi {
-            if sum.abs() > 1e-10 {
-                tracing::warn!(
-                    "Possible failure to converge @ {}: s={}, s'={}",
-                    guess,
-                    sum,
-                    sum_prime
-                );
-            }
-            return guess;
+            return NewtonResult {
+                root: guess,
+                iterations,
+                residual: sum,
+                converged: sum.abs() <= 1e-10,
+            };
+        }
+    }
+}
+
+// Finds a bracket containing the zero of a monotonic function f, without hard-coding any
+// bounds. Starting from an initial guess, the bracket widens geometrically in both directions,
+// beginning with a half-width of scale, until f changes sign. If the guess and scale are
+// expressed in the same units as f's argument, so is the bracket. Returns None if the guess
+// or scale is invalid, or if f doesn't change sign within the finite floats.
+pub fn find_bracket(guess: f64, scale: f64, f: impl Fn(f64) -> f64) -> Option<(f64, f64)> {
+    if !guess.is_finite() || scale.is_nan() || scale <= 0. {
+        return None;
+    }
+    let f_guess = f(guess);
+    if f_guess == 0. {
+        return Some((guess - scale, guess + scale));
+    }
+    let mut step = scale;
+    loop {
+        let (lo, hi) = (guess - step, guess + step);
+        if !lo.is_finite() || !hi.is_finite() {
+            return None;
+        }
+        if f(lo) * f_guess <= 0. {
+            return Some((lo, guess));
+        }
+        if f(hi) * f_guess <= 0. {
+            return Some((guess, hi));
         }
+        step *= 2.;
+    }
+}
+
+// Same as solve_newton(), but choosing the bracket automatically with find_bracket()
+pub fn solve_newton_from(guess: f64, scale: f64, f: impl Fn(f64) -> (f64, f64)) -> f64 {
+    solve_newton_from_with_stats(guess, scale, f).root_or_warn()
+}
+
+// Same as solve_newton_with_stats(), but choosing the bracket automatically with find_bracket().
+// If no bracket is found, the result is the unconverged initial guess.
+pub fn solve_newton_from_with_stats(
+    guess: f64,
+    scale: f64,
+    f: impl Fn(f64) -> (f64, f64),
+) -> NewtonResult {
+    match find_bracket(guess, scale, |x| f(x).0) {
+        Some(bounds) => solve_newton_with_stats(bounds, f),
+        None => NewtonResult {
+            root: guess,
+            iterations: 0,
+            residual: f(guess).0,
+            converged: false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unbracketable_zero() {
+        // A linear function is bracketed from far away
+        let result = solve_newton_from_with_stats(1e6, 1., |x| (x - 3., 1.));
+        assert!(result.converged && (result.root - 3.).abs() < 1e-9);
+
+        // Without a sign change or a valid guess, the solver reports failure instead of panicking
+        let positive = solve_newton_from_with_stats(0., 1., |_| (1., 0.));
+        assert!(!positive.converged);
+        let nan_guess = solve_newton_from_with_stats(f64::NAN, 1., |x| (x, 1.));
+        assert!(!nan_guess.converged);
+
+        let mut stats = SolverStats::default();
+        stats.record(&positive);
+        stats.record(&nan_guess);
+        assert_eq!((stats.solves, stats.failures), (2, 2));
     }
 }
@@ -0,0 +1,218 @@
+//! Offline smoothing of rating histories, in the spirit of Whole-History Rating and
+//! TrueSkill Through Time. Every system in `systems` is a filter: a player's rating after
+//! some event only uses evidence from that event and earlier. For historical analysis, we can
+//! afford to also revise past ratings using evidence from later events.
+use crate::systems::{EloMMR, Player, PlayerEvent, Rating};
+
+/// How skill evolves between a player's consecutive events: its difference from `limit` shrinks
+/// by a factor of `decay`, after which Gaussian noise with deviation `sig_drift` is added.
+#[derive(Clone, Copy, Debug)]
+pub struct Transition {
+    pub decay: f64,
+    pub limit: Rating,
+    pub sig_drift: f64,
+}
+
+/// Smooths rating histories by modeling skill as a linear Gaussian process. The forward pass is
+/// the one already performed by the rating system, whose filtered estimates are recorded in each
+/// player's `event_history`; this struct runs the corresponding Rauch-Tung-Striebel backward pass.
+///
+/// Only Elo-MMR histories are supported, since the transitions come from its drift and decay.
+/// Players don't keep their posteriors from past events, so the backward pass starts from each
+/// event's `rating_mu` and `rating_sig`, which were rounded to integers. The Gaussian view also
+/// ignores the logistic shape of Elo-MMR's factors, so the result is an approximation.
+#[derive(Debug, Default)]
+pub struct HistorySmoother {
+    /// The system that produced the histories, whose drift and inactivity decay determine
+    /// the transition between each pair of events
+    pub system: EloMMR,
+}
+
+impl HistorySmoother {
+    /// The transition into a contest with the given weight, which a player enters after
+    /// `times_played` earlier events and an absence of `delta_secs`
+    pub fn transition(
+        &self,
+        contest_weight: f64,
+        times_played: usize,
+        delta_secs: f64,
+    ) -> Transition {
+        let weight = self.system.compute_weight(contest_weight, times_played);
+        let sig_drift = self.system.compute_sig_drift(weight, delta_secs);
+        match &self.system.inactivity_decay {
+            Some(inactivity_decay) => Transition {
+                decay: inactivity_decay.decay_factor(delta_secs),
+                limit: inactivity_decay.limit,
+                sig_drift,
+            },
+            None => Transition {
+                decay: 1.,
+                limit: Rating { mu: 0., sig: 0. },
+                sig_drift,
+            },
+        }
+    }
+
+    /// Given the filtered ratings after each event, and the transition from each event to the
+    /// next, returns the smoothed ratings at each event.
+    pub fn smooth_ratings(filtered: &[Rating], transitions: &[Transition]) -> Vec<Rating> {
+        assert_eq!(transitions.len() + 1, filtered.len().max(1));
+        let mut smoothed = filtered.to_vec();
+        for t in (0..transitions.len()).rev() {
+            let Transition {
+                decay,
+                limit,
+                sig_drift,
+            } = transitions[t];
+            let var_filtered = filtered[t].sig.powi(2);
+            let mu_predicted = limit.mu + decay * (filtered[t].mu - limit.mu);
+            let var_predicted = decay * decay * var_filtered
+                + (1. - decay * decay) * limit.sig.powi(2)
+                + sig_drift * sig_drift;
+            let next = smoothed[t + 1];
+
+            // The gain says how much of the next event's revision carries back to this one
+            let gain = decay * var_filtered / var_predicted;
+            let mu = filtered[t].mu + gain * (next.mu - mu_predicted);
+            let var = var_filtered + gain * gain * (next.sig.powi(2) - var_predicted);
+            smoothed[t] = Rating {
+                mu,
+                sig: var.max(0.).sqrt(),
+            };
+        }
+        smoothed
+    }
+
+    /// Returns a copy of the history in which each event's rating is replaced by its smoothed
+    /// estimate, so that it can be written in the same format as the original. The time and
+    /// weight of each contest are looked up in `contests` by the events' `contest_index`, which
+    /// fails if the history came from a different list of contests. Since events are numbered
+    /// by their position in the filtered source, `contests` should list all of its contests,
+    /// including any that were skipped.
+    pub fn smooth_history(
+        &self,
+        history: &[PlayerEvent],
+        contests: &[(u64, f64)],
+    ) -> Result<Vec<PlayerEvent>, String> {
+        let lookup = |event: &PlayerEvent| {
+            contests.get(event.contest_index).copied().ok_or_else(|| {
+                format!(
+                    "Event in contest {} is out of range: only {} contests were given",
+                    event.contest_index,
+                    contests.len()
+                )
+            })
+        };
+        let filtered: Vec<Rating> = history
+            .iter()
+            .map(|event| Rating {
+                mu: event.rating_mu as f64,
+                sig: event.rating_sig as f64,
+            })
+            .collect();
+        let transitions = history
+            .windows(2)
+            .enumerate()
+            .map(|(t, events)| {
+                let (time_before, _) = lookup(&events[0])?;
+                let (time_after, weight) = lookup(&events[1])?;
+                let delta_secs = time_after.saturating_sub(time_before) as f64;
+                Ok(self.transition(weight, t + 1, delta_secs))
+            })
+            .collect::<Result<Vec<Transition>, String>>()?;
+        let smoothed = Self::smooth_ratings(&filtered, &transitions);
+
+        Ok(history
+            .iter()
+            .zip(smoothed)
+            .map(|(event, rating)| PlayerEvent {
+                rating_mu: rating.mu.round() as i32,
+                rating_sig: rating.sig.round() as i32,
+                ..*event
+            })
+            .collect())
+    }
+
+    /// Convenience wrapper to smooth a player's entire history.
+    pub fn smooth_player(
+        &self,
+        player: &Player,
+        contests: &[(u64, f64)],
+    ) -> Result<Vec<PlayerEvent>, String> {
+        self.smooth_history(&player.event_history, contests)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_smoothing_revises_the_past() {
+        let filtered = [
+            Rating {
+                mu: 1500.,
+                sig: 300.,
+            },
+            Rating {
+                mu: 1600.,
+                sig: 200.,
+            },
+            Rating {
+                mu: 1900.,
+                sig: 150.,
+            },
+        ];
+        let smoother = HistorySmoother::default();
+        let transitions = [
+            smoother.transition(1., 1, 0.),
+            smoother.transition(1., 2, 0.),
+        ];
+        let smoothed = HistorySmoother::smooth_ratings(&filtered, &transitions);
+
+        // The latest rating has no future evidence, so it remains as it was
+        assert_eq!(smoothed[2].mu, filtered[2].mu);
+        assert_eq!(smoothed[2].sig, filtered[2].sig);
+        // Earlier ratings move toward later evidence, and become more certain
+        for t in 0..2 {
+            assert!(filtered[t].mu < smoothed[t].mu && smoothed[t].mu < filtered[2].mu);
+            assert!(smoothed[t].sig < filtered[t].sig);
+        }
+
+        // After a long absence, later evidence says less about the past
+        let smoother = HistorySmoother {
+            system: EloMMR {
+                drift_per_day: 100.,
+                ..EloMMR::default()
+            },
+        };
+        let absent = [transitions[0], smoother.transition(1., 2, 365. * 86_400.)];
+        let smoothed_absent = HistorySmoother::smooth_ratings(&filtered, &absent);
+        assert!(smoothed_absent[1].mu < smoothed[1].mu);
+        assert!(smoothed_absent[1].sig > smoothed[1].sig);
+    }
+
+    #[test]
+    fn test_smoothing_checks_contest_indices() {
+        let event = |contest_index, rating_mu| PlayerEvent {
+            contest_index,
+            rating_mu,
+            rating_sig: 200,
+            perf_score: 0,
+            place: 0,
+        };
+        let history = [event(0, 1500), event(2, 1600)];
+        let smoother = HistorySmoother::default();
+
+        // The history refers to a contest that isn't in the list it was given
+        assert!(
+            smoother
+                .smooth_history(&history, &[(0, 1.), (86_400, 1.)])
+                .is_err()
+        );
+        let contests = [(0, 1.), (86_400, 1.), (172_800, 1.)];
+        let smoothed = smoother.smooth_history(&history, &contests).unwrap();
+        assert_eq!(smoothed[1], history[1]);
+        assert!(smoothed[0].rating_mu > 1500);
+    }
+}
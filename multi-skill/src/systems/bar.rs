@@ -31,6 +31,21 @@ impl BAR {
 }
 
 impl RatingSystem for BAR {
+    // The performance is treated like the outcome of one highly informative match,
+    // as in round_update()
+    fn individual_update(&self, params: ContestRatingParams, player: &mut Player, mu_perf: f64) {
+        let sig_perf_sq = self.beta.powi(2) / params.weight;
+        player.add_noise_and_collapse(self.sig_drift);
+
+        let my_rating = &player.approx_posterior;
+        let old_sig_sq = my_rating.sig.powi(2);
+        let info = 0.25 * old_sig_sq / (old_sig_sq + 2. * sig_perf_sq);
+        let sig = my_rating.sig * self.kappa.max(1. - info).sqrt();
+        let mu = my_rating.mu + info * (mu_perf - my_rating.mu);
+
+        player.update_rating(Rating { mu, sig }, mu_perf);
+    }
+
     fn round_update(
         &self,
         params: ContestRatingParams,
@@ -1,9 +1,10 @@
 //! Codeforces system details: https://codeforces.com/blog/entry/20762
 
-use super::{Player, Rating, RatingSystem, robust_average};
+use super::{Player, Rating, RatingSystem, robust_average_with_stats};
 use crate::data_processing::ContestRatingParams;
-use crate::numerical::{TANH_MULTIPLIER, standard_logistic_cdf};
+use crate::numerical::{NewtonResult, SolverStats, TANH_MULTIPLIER, standard_logistic_cdf};
 use rayon::prelude::*;
+use std::sync::Mutex;
 
 #[derive(Debug)]
 pub struct CodeforcesSys {
@@ -22,7 +23,8 @@ impl Default for CodeforcesSys {
 
 impl CodeforcesSys {
     // ratings is a list of the participants, ordered from first to last place
-    // returns: performance of the player in ratings[id] who tied against ratings[lo..hi]
+    // returns: performance of the player in ratings[id] who tied against ratings[lo..hi],
+    // along with the solver's diagnostics
     fn compute_performance(
         &self,
         sig_perf: f64,
@@ -30,7 +32,7 @@ impl CodeforcesSys {
         worse: &[Rating],
         all: &[Rating],
         my_rating: Rating,
-    ) -> f64 {
+    ) -> NewtonResult {
         // The conversion is 2*rank - 1/my_sig = 2*pos_offset + tied_offset = pos - neg + all
         // Note: the caller currently guarantees that every .sig equals sig_perf
         let pos_offset: f64 = better.iter().map(|rating| rating.sig.recip()).sum();
@@ -46,12 +48,11 @@ impl CodeforcesSys {
 
         let geo_rank = (ac_rank * ex_rank).sqrt();
         let geo_offset = 2. * geo_rank - my_rating.sig.recip() - all_offset;
-        let geo_rating = robust_average(
+        robust_average_with_stats(
             all.iter().cloned().map(Into::into),
             TANH_MULTIPLIER * geo_offset,
             0.,
-        );
-        geo_rating
+        )
     }
 
     fn win_probability(&self, sig_perf: f64, player: &Rating, foe: &Rating) -> f64 {
@@ -61,11 +62,28 @@ impl CodeforcesSys {
 }
 
 impl RatingSystem for CodeforcesSys {
+    fn individual_update(&self, params: ContestRatingParams, player: &mut Player, mu_perf: f64) {
+        let geo_perf = mu_perf.min(params.perf_ceiling);
+        let wt = params.weight * self.weight;
+        let mu = (player.approx_posterior.mu + wt * geo_perf) / (1. + wt);
+        let sig = player.approx_posterior.sig;
+        player.update_rating(Rating { mu, sig }, geo_perf);
+    }
+
     fn round_update(
         &self,
         params: ContestRatingParams,
         standings: Vec<(&mut Player, usize, usize)>,
     ) {
+        self.round_update_with_stats(params, standings, &[]);
+    }
+
+    fn round_update_with_stats(
+        &self,
+        params: ContestRatingParams,
+        standings: Vec<(&mut Player, usize, usize)>,
+        _scores: &[Option<f64>],
+    ) -> SolverStats {
         let sig_perf = self.beta / params.weight.sqrt();
         let all_ratings: Vec<Rating> = standings
             .par_iter()
@@ -75,23 +93,25 @@ impl RatingSystem for CodeforcesSys {
             })
             .collect();
 
+        let stats = Mutex::new(SolverStats::default());
         standings
             .into_par_iter()
             .zip(all_ratings.par_iter())
             .for_each(|((player, lo, hi), &my_rating)| {
-                let geo_perf = self
-                    .compute_performance(
-                        sig_perf,
-                        &all_ratings[..lo],
-                        &all_ratings[hi + 1..],
-                        &all_ratings,
-                        my_rating,
-                    )
-                    .min(params.perf_ceiling);
+                let result = self.compute_performance(
+                    sig_perf,
+                    &all_ratings[..lo],
+                    &all_ratings[hi + 1..],
+                    &all_ratings,
+                    my_rating,
+                );
+                stats.lock().unwrap().record(&result);
+                let geo_perf = result.root_or_warn().min(params.perf_ceiling);
                 let wt = params.weight * self.weight;
                 let mu = (my_rating.mu + wt * geo_perf) / (1. + wt);
                 let sig = player.approx_posterior.sig;
                 player.update_rating(Rating { mu, sig }, geo_perf);
             });
+        stats.into_inner().unwrap()
     }
 }
@@ -1,11 +1,14 @@
 mod player;
+mod tanh_terms;
 
-use crate::data_processing::{Contest, ContestRatingParams};
-use crate::numerical::{TANH_MULTIPLIER, solve_newton};
+use super::{SECS_PER_DAY, SIG_CATEGORY_NOOB};
+use crate::data_processing::{Contest, ContestRatingParams, Violation};
+use crate::numerical::{NewtonResult, SolverStats, TANH_MULTIPLIER, solve_newton_from_with_stats};
 pub use player::{Player, PlayerEvent};
 use serde::{Deserialize, Serialize};
 use std::cell::{RefCell, RefMut};
 use std::collections::HashMap;
+pub use tanh_terms::{TanhTable, TanhTerms};
 
 pub type PlayersByName = HashMap<String, RefCell<Player>>;
 
@@ -23,8 +26,8 @@ impl Rating {
         }
     }
 
-    // TODO: consider making time_decay head towards a limit (mu_noob, sig_moob),
-    //       or alternatively keep mu the same while having sig -> sig_noob.
+    // Pulls the rating toward a limit, shrinking the difference in means by a factor of decay
+    // and the difference in variances by a factor of decay squared
     pub fn towards_noise(self, decay: f64, limit: Self) -> Self {
         let mu_diff = self.mu - limit.mu;
         let sig_sq_diff = self.sig * self.sig - limit.sig * limit.sig;
@@ -35,6 +38,34 @@ impl Rating {
     }
 }
 
+/// A policy for pulling the ratings of long-absent players toward a limit, such as the
+/// newcomer rating, so that returning players don't keep stale ratings
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct InactivityDecay {
+    /// The rating toward which an arbitrarily long absence decays
+    pub limit: Rating,
+    /// The number of days, after the grace period, in which a rating moves halfway to the limit
+    pub half_life_days: f64,
+    /// The number of days of absence that incur no decay
+    pub grace_days: f64,
+}
+
+impl InactivityDecay {
+    /// The factor by which a rating's difference from the limit shrinks after an absence
+    pub fn decay_factor(&self, delta_secs: f64) -> f64 {
+        let decay_days = (delta_secs / SECS_PER_DAY - self.grace_days).max(0.);
+        0.5f64.powf(decay_days / self.half_life_days)
+    }
+
+    /// Applies the decay to a player, given the time since their previous contest
+    pub fn apply(&self, player: &mut Player) {
+        let decay = self.decay_factor(player.delta_time as f64);
+        if decay < 1. {
+            player.decay_and_collapse(decay, self.limit);
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct TanhTerm {
     pub mu: f64,
@@ -75,7 +106,35 @@ pub fn robust_average(
     offset: f64,
     slope: f64,
 ) -> f64 {
-    let bounds = (-6000.0, 9000.0);
+    robust_average_with_stats(all_ratings, offset, slope).root_or_warn()
+}
+
+// Same as robust_average(), but reporting on the solver's convergence
+pub fn robust_average_with_stats(
+    all_ratings: impl Iterator<Item = TanhTerm> + Clone,
+    offset: f64,
+    slope: f64,
+) -> NewtonResult {
+    // Start the search from the zero of the linearized function, i.e., a weighted mean
+    // of the mu_i's, looking first within the typical width of a tanh term
+    let (sum_w, sum_w_mu, sum_w_out) =
+        all_ratings
+            .clone()
+            .fold((slope, -offset, 0.), |(sw, swm, swo), term| {
+                let w = term.w_arg * term.w_out;
+                (sw + w, swm + w * term.mu, swo + term.w_out)
+            });
+    let guess = sum_w_mu / sum_w;
+    if sum_w_out == 0. {
+        // Without tanh terms, the function is linear
+        return NewtonResult {
+            root: guess,
+            iterations: 0,
+            residual: 0.,
+            converged: true,
+        };
+    }
+    let scale = sum_w_out / (sum_w - slope);
     let f = |x: f64| -> (f64, f64) {
         all_ratings
             .clone()
@@ -90,7 +149,7 @@ pub fn robust_average(
                 (s + v, sp + vp)
             })
     };
-    solve_newton(bounds, f)
+    solve_newton_from_with_stats(guess, scale, f)
 }
 
 pub trait RatingSystem: std::fmt::Debug {
@@ -100,9 +159,79 @@ pub trait RatingSystem: std::fmt::Debug {
         standings: Vec<(&mut Player, usize, usize)>,
     );
 
+    /// Like round_update(), but also given each contestant's numerical score, if known, in the
+    /// same order as the standings. By default, scores are ignored and only ranks are used.
+    fn round_update_with_scores(
+        &self,
+        params: ContestRatingParams,
+        standings: Vec<(&mut Player, usize, usize)>,
+        _scores: &[Option<f64>],
+    ) {
+        self.round_update(params, standings);
+    }
+
+    /// Like round_update_with_scores(), but also reports on the convergence of any numerical
+    /// solvers used in the update. By default, no solver statistics are collected.
+    fn round_update_with_stats(
+        &self,
+        params: ContestRatingParams,
+        standings: Vec<(&mut Player, usize, usize)>,
+        scores: &[Option<f64>],
+    ) -> SolverStats {
+        self.round_update_with_scores(params, standings, scores);
+        SolverStats::default()
+    }
+
     /// Computes the update for only one player, given their contest performance
     fn individual_update(&self, _params: ContestRatingParams, _player: &mut Player, _mu_perf: f64) {
-        unimplemented!("Currently, only SimpleEloMMR allows performance-based updates");
+        unimplemented!("{:?} does not allow performance-based updates", self);
+    }
+
+    /// Creates a temporary player to represent a team in round_update().
+    /// By default, the team's rating averages those of its members.
+    fn make_team(&self, _params: ContestRatingParams, members: &[&Player]) -> Player {
+        let n = members.len() as f64;
+        let mu = members.iter().map(|m| m.approx_posterior.mu).sum::<f64>() / n;
+        let sig_sq = members
+            .iter()
+            .map(|m| m.approx_posterior.sig.powi(2))
+            .sum::<f64>()
+            / n;
+        Player::for_team(mu, sig_sq.sqrt(), members)
+    }
+
+    /// Transfers a team's update onto one of its members, given the team's states before and
+    /// after round_update(), and the unrounded performance that round_update() gave the team.
+    /// The member's placeholder event must already have been pushed.
+    /// By default, the member takes a share of the team's update in proportion to its share of
+    /// the team's variance, capped at the whole update. Thus, members who are as uncertain as
+    /// their team shift by the same amount, while their uncertainty shrinks in the same proportion.
+    /// Returns the convergence statistics of any numerical solvers used in the transfer.
+    fn infer_from_team(
+        &self,
+        _params: ContestRatingParams,
+        team_before: &Player,
+        team_after: &Player,
+        team_perf: f64,
+        member: &mut Player,
+    ) -> SolverStats {
+        let rating = condition_on_sum(
+            member.approx_posterior,
+            team_before.approx_posterior,
+            team_after.approx_posterior,
+        );
+        member.update_rating(rating, team_perf);
+        SolverStats::default()
+    }
+}
+
+// Approximately conditions one summand's rating on an update to the whole sum
+fn condition_on_sum(part: Rating, before: Rating, after: Rating) -> Rating {
+    let gain = (part.sig / before.sig).powi(2).min(1.);
+    let sig_sq = part.sig.powi(2) - gain * gain * (before.sig.powi(2) - after.sig.powi(2));
+    Rating {
+        mu: part.mu + gain * (after.mu - before.mu),
+        sig: sig_sq.sqrt(),
     }
 }
 
@@ -110,6 +239,46 @@ pub fn outcome_free<T>(standings: &[(T, usize, usize)]) -> bool {
     standings.is_empty() || standings[0].2 + 1 >= standings.len()
 }
 
+// A standings entry whose rating is being updated, holding the guards to its players
+enum Entrant<'a> {
+    Individual(RefMut<'a, Player>),
+    Team {
+        team: Box<Player>,
+        team_before: Box<Player>,
+        members: Vec<RefMut<'a, Player>>,
+    },
+    // A scratch copy of an unrated entry, to be discarded after the update
+    Unrated(Box<Player>),
+    Categorized {
+        combined: Box<Player>,
+        combined_before: Box<Player>,
+        player: RefMut<'a, Player>,
+    },
+}
+
+impl Entrant<'_> {
+    fn player_mut(&mut self) -> &mut Player {
+        match self {
+            Self::Individual(player) => player,
+            Self::Team { team, .. } => team,
+            Self::Categorized { combined, .. } => combined,
+            Self::Unrated(scratch) => scratch,
+        }
+    }
+}
+
+fn push_event(player: &mut Player, contest_index: usize, place: usize, time_seconds: u64) {
+    player.event_history.push(PlayerEvent {
+        contest_index,
+        rating_mu: 0,  // will be filled by system.round_update()
+        rating_sig: 0, // will be filled by system.round_update()
+        perf_score: 0, // will be filled by system.round_update()
+        place,
+    });
+    player.delta_time = time_seconds - player.update_time;
+    player.update_time = time_seconds;
+}
+
 pub fn simulate_contest(
     players: &mut PlayersByName,
     contest: &Contest,
@@ -118,78 +287,234 @@ pub fn simulate_contest(
     sig_newbie: f64,
     contest_index: usize,
 ) {
+    simulate_contest_seeded(
+        players,
+        contest,
+        system,
+        mu_newbie,
+        sig_newbie,
+        contest_index,
+        &|_| None,
+    );
+}
+
+/// Like simulate_contest(), but first-time players may be seeded with an initial rating from
+/// external data, such as their rating on another platform. Unseeded first-time players get
+/// the contest's newcomer prior if it has one, or else the global default.
+/// Returns the convergence statistics of the system's numerical solvers on this contest.
+pub fn simulate_contest_seeded(
+    players: &mut PlayersByName,
+    contest: &Contest,
+    system: &dyn RatingSystem,
+    mu_newbie: f64,
+    sig_newbie: f64,
+    contest_index: usize,
+    seed: &dyn Fn(&str) -> Option<Rating>,
+) -> SolverStats {
     if outcome_free(&contest.standings) {
         tracing::warn!(
             "Ignoring contest {} because all players tied",
             contest_index
         );
-        return;
+        return SolverStats::default();
+    }
+
+    // Unrated entries are only read, so a handle that they share with another entry must be
+    // caught here, or else whether it's caught would depend on the order of the standings
+    for violation in contest.violations() {
+        if let Violation::DuplicateHandle { handle, .. } = violation {
+            panic!("Duplicate handle {} in contest {}", handle, contest_index);
+        }
     }
 
     // If a player is competing for the first time, initialize with a default rating
-    contest.standings.iter().for_each(|(handle, _, _)| {
-        // TODO TEAMS: make an entry for every member of the team, then make the team object
-        //             in teams: PlayersByName with system.make_team(players)
-        players.entry(handle.clone()).or_insert_with(|| {
-            RefCell::new(Player::with_rating(
-                mu_newbie,
-                sig_newbie,
-                contest.time_seconds,
-            ))
-        });
-    });
+    let newcomer = |handle: &str| {
+        let rating = seed(handle)
+            .or(contest.rating_params.newcomer_prior)
+            .unwrap_or(Rating {
+                mu: mu_newbie,
+                sig: sig_newbie,
+            });
+        Player::with_rating(rating.mu, rating.sig, contest.time_seconds)
+    };
+    for handle in contest
+        .standings
+        .iter()
+        .filter(|(name, _, _)| !contest.unrated.contains(name))
+        .flat_map(|(name, _, _)| contest.members(name))
+    {
+        players
+            .entry(handle.clone())
+            .or_insert_with(|| RefCell::new(newcomer(handle)));
+    }
 
     // Low-level magic: verify that handles are distinct and store guards so that the cells
     // can be released later. This setup enables safe parallel processing.
-    let mut guards: Vec<RefMut<Player>> = contest
+    let borrow = |handle: &String| {
+        players
+            .get(handle)
+            .expect("Uninitialized handle")
+            .try_borrow_mut()
+            .expect("Duplicate handle")
+    };
+    // Unrated players are only read, and may not even have been initialized
+    let snapshot = |handle: &String| match players.get(handle) {
+        Some(player) => player.try_borrow().expect("Duplicate handle").clone(),
+        None => newcomer(handle),
+    };
+    let mut entrants: Vec<Entrant> = contest
         .standings
         .iter()
-        // TODO TEAMS: if individual, get guard to that, else get guard to its team
-        .map(|(handle, _, _)| {
-            players
-                .get(handle)
-                .expect("Uninitialized handle")
-                .try_borrow_mut()
-                .expect("Duplicate handle")
+        .map(|(name, _, _)| match contest.teams.get(name) {
+            team if contest.unrated.contains(name) => {
+                let mut members: Vec<Player> = contest.members(name).iter().map(snapshot).collect();
+                let scratch = match team {
+                    Some(_) => {
+                        system.make_team(contest.rating_params, &members.iter().collect::<Vec<_>>())
+                    }
+                    // An individual's copy keeps their whole state, such as their rating's
+                    // factors, unless it must combine their rating with a category offset
+                    None => {
+                        let player = members.pop().expect("Unrated entry has no player");
+                        let category = contest.category.as_deref();
+                        match category.filter(|&c| player.category_offsets.contains_key(c)) {
+                            Some(category) => {
+                                let rating = player.rating_in(Some(category));
+                                Player::for_team(rating.mu, rating.sig, &[&player])
+                            }
+                            None => player,
+                        }
+                    }
+                };
+                Entrant::Unrated(Box::new(scratch))
+            }
+            Some(members) => {
+                assert!(!members.is_empty(), "Team {} has no members", name);
+                let members: Vec<RefMut<Player>> = members.iter().map(borrow).collect();
+                let member_refs: Vec<&Player> = members.iter().map(|m| &**m).collect();
+                let team = Box::new(system.make_team(contest.rating_params, &member_refs));
+                Entrant::Team {
+                    team_before: team.clone(),
+                    team,
+                    members,
+                }
+            }
+            None => match &contest.category {
+                Some(category) => {
+                    let mut player = borrow(name);
+                    player
+                        .category_offsets
+                        .entry(category.clone())
+                        .or_insert(Rating {
+                            mu: 0.,
+                            sig: SIG_CATEGORY_NOOB,
+                        });
+                    let rating = player.rating_in(Some(category));
+                    let combined = Box::new(Player::for_team(rating.mu, rating.sig, &[&player]));
+                    Entrant::Categorized {
+                        combined_before: combined.clone(),
+                        combined,
+                        player,
+                    }
+                }
+                None => Entrant::Individual(borrow(name)),
+            },
         })
         .collect();
 
     // Update player metadata and get &mut references to all requested players
-    let standings: Vec<(&mut Player, usize, usize)> = guards
+    let standings: Vec<(&mut Player, usize, usize)> = entrants
         .iter_mut()
-        .map(std::ops::DerefMut::deref_mut)
+        .map(Entrant::player_mut)
         .zip(contest.standings.iter())
         .map(|(player, &(_, lo, hi))| {
-            player.event_history.push(PlayerEvent {
-                contest_index,
-                rating_mu: 0,  // will be filled by system.round_update()
-                rating_sig: 0, // will be filled by system.round_update()
-                perf_score: 0, // will be filled by system.round_update()
-                place: lo,
-            });
-            player.delta_time = contest.time_seconds - player.update_time;
-            player.update_time = contest.time_seconds;
+            push_event(player, contest_index, lo, contest.time_seconds);
             (player, lo, hi)
         })
         .collect();
 
-    system.round_update(contest.rating_params, standings);
+    let mut stats = if contest.scores.is_empty() {
+        system.round_update_with_stats(contest.rating_params, standings, &[])
+    } else {
+        let scores: Vec<Option<f64>> = contest
+            .standings
+            .iter()
+            .map(|(name, _, _)| contest.scores.get(name).copied())
+            .collect();
+        system.round_update_with_stats(contest.rating_params, standings, &scores)
+    };
 
-    // TODO TEAMS: each participant uses its team's update using system.infer_from_team(),
-    // making sure to copy the team's event_history metadata as well
+    // Each team member infers their own update from their team's, and each categorized player
+    // splits their update between their general skill and their category offset
+    for (entrant, &(_, lo, _)) in entrants.iter_mut().zip(contest.standings.iter()) {
+        match entrant {
+            Entrant::Individual(_) | Entrant::Unrated(_) => {}
+            Entrant::Team {
+                team,
+                team_before,
+                members,
+            } => {
+                for member in members {
+                    push_event(member, contest_index, lo, contest.time_seconds);
+                    stats += system.infer_from_team(
+                        contest.rating_params,
+                        team_before,
+                        team,
+                        team.latest_perf,
+                        member,
+                    );
+                }
+            }
+            Entrant::Categorized {
+                combined,
+                combined_before,
+                player,
+            } => {
+                push_event(player, contest_index, lo, contest.time_seconds);
+
+                // The combined update is split once between the two summands, each taking a
+                // share in proportion to its share of the combined variance
+                let (before, after) = (combined_before.approx_posterior, combined.approx_posterior);
+                let category = contest.category.as_ref();
+                if let Some(offset) = category.and_then(|c| player.category_offsets.get_mut(c)) {
+                    *offset = condition_on_sum(*offset, before, after);
+                }
+                let general = condition_on_sum(player.approx_posterior, before, after);
+                player.update_rating_and_absorb(general, combined.latest_perf);
+            }
+        }
+    }
+    stats
 }
 
+/// The ratings of a contest's entrants who already have at least min_history events.
+/// Teams are rated as in simulate_contest(), from whichever of their members have ratings.
 pub fn get_participant_ratings(
     players: &mut PlayersByName,
-    contest_standings: &[(String, usize, usize)],
+    contest: &Contest,
+    system: &dyn RatingSystem,
     min_history: usize,
 ) -> Vec<(Rating, usize, usize)> {
     let mut standings: Vec<(Rating, usize, usize)> = vec![];
+    let category = contest.category.as_deref();
 
-    for &(ref handle, lo, hi) in contest_standings {
-        if let Some(player) = players.get(handle).map(RefCell::borrow) {
+    for &(ref name, lo, hi) in &contest.standings {
+        if let Some(members) = contest.teams.get(name) {
+            let members: Vec<_> = members
+                .iter()
+                .filter_map(|handle| players.get(handle).map(RefCell::borrow))
+                .collect();
+            if members.is_empty() {
+                continue;
+            }
+            let member_refs: Vec<&Player> = members.iter().map(|m| &**m).collect();
+            let team = system.make_team(contest.rating_params, &member_refs);
+            if team.times_played() >= min_history {
+                standings.push((team.approx_posterior, lo, hi));
+            }
+        } else if let Some(player) = players.get(name).map(RefCell::borrow) {
             if player.times_played() >= min_history {
-                standings.push((player.approx_posterior, lo, hi));
+                standings.push((player.rating_in(category), lo, hi));
             }
         }
     }
@@ -214,3 +539,268 @@ pub fn get_participant_ratings(
     }
     standings
 }
+
+/// Rates a single contest among new players with the given priors and places, passing them
+/// straight to round_update(), and returns their posteriors.
+#[cfg(test)]
+pub(crate) fn rate_new_players(
+    system: &dyn RatingSystem,
+    priors: &[Rating],
+    places: &[(usize, usize)],
+) -> Vec<Rating> {
+    let mut players: Vec<Player> = priors
+        .iter()
+        .zip(places)
+        .map(|(prior, &(lo, _))| {
+            let mut player = Player::with_rating(prior.mu, prior.sig, 0);
+            push_event(&mut player, 0, lo, 0);
+            player
+        })
+        .collect();
+    let standings = players
+        .iter_mut()
+        .zip(places)
+        .map(|(player, &(lo, hi))| (player, lo, hi))
+        .collect();
+    system.round_update(ContestRatingParams::default(), standings);
+    players
+        .iter()
+        .map(|player| player.approx_posterior)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::systems::{
+        BAR, CodeforcesSys, EloMMR, Glicko, SimpleEloMMR, TopcoderSys, TrueSkillSPb,
+    };
+
+    #[test]
+    fn test_team_updates_reach_members() {
+        let mut contest = Contest::new(0);
+        contest.push_team("winners", vec!["alice".into(), "bob".into()]);
+        contest.push_contestant("carol");
+        contest.push_team("losers", vec!["dave".into(), "eve".into()]);
+
+        let mut players = PlayersByName::new();
+        let system = TrueSkillSPb::default();
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 0);
+
+        assert_eq!(players.len(), 5);
+        assert!(!players.contains_key("winners"));
+        for (handle, place) in [("alice", 0), ("bob", 0), ("carol", 1), ("dave", 2)] {
+            let player = players[handle].borrow();
+            assert_eq!(player.event_history.len(), 1);
+            assert_eq!(player.event_history[0].place, place);
+            assert!(player.approx_posterior.sig < 350.);
+        }
+        let alice = players["alice"].borrow().approx_posterior;
+        let dave = players["dave"].borrow().approx_posterior;
+        assert!(alice.mu > 1500.);
+        assert!(dave.mu < 1500.);
+
+        // Teams are rated through their members for evaluation, too
+        let ratings = get_participant_ratings(&mut players, &contest, &system, 1);
+        let places: Vec<_> = ratings.iter().map(|&(_, lo, _)| lo).collect();
+        assert_eq!(places, vec![0, 1, 2]);
+        assert!(ratings[0].0.mu > ratings[2].0.mu);
+    }
+
+    #[test]
+    fn test_category_offsets() {
+        let mut contest = Contest::new(0);
+        contest.category = Some("latin".into());
+        contest.push_contestant("alice");
+        contest.push_contestant("bob");
+
+        let mut players = PlayersByName::new();
+        let system = EloMMR::default();
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 0);
+
+        // The winner's general skill and latin offset both go up, sharing the update in
+        // proportion to their prior variances
+        let alice = players["alice"].borrow();
+        let offset = alice.category_offsets["latin"];
+        assert_eq!(alice.event_history.len(), 1);
+        assert!(alice.approx_posterior.mu > 1500.);
+        assert!(offset.mu > 0. && offset.sig < SIG_CATEGORY_NOOB);
+        let share_ratio = (350. / SIG_CATEGORY_NOOB).powi(2);
+        assert!(((alice.approx_posterior.mu - 1500.) / offset.mu - share_ratio).abs() < 1e-9);
+        let latin = alice.rating_in(Some("latin"));
+        assert_eq!(latin.mu, alice.approx_posterior.mu + offset.mu);
+        assert_eq!(alice.rating_in(Some("waltz")).mu, alice.approx_posterior.mu);
+        drop(alice);
+
+        // Uncategorized contests leave the offsets alone
+        contest.category = None;
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 1);
+        assert_eq!(
+            players["bob"].borrow().category_offsets["latin"].mu,
+            -offset.mu
+        );
+    }
+
+    #[test]
+    fn test_categorized_contests_keep_factors() {
+        // The number of logistic factors and the normal factor's mean, as saved in checkpoints
+        let factors = |player: &Player| {
+            let saved = serde_json::to_value(player).unwrap();
+            let num_logistic = saved["logistic_factors"].as_array().unwrap().len();
+            (num_logistic, saved["normal_factor"]["mu"].as_f64().unwrap())
+        };
+        let mut contest = Contest::new(0);
+        contest.push_contestant("alice");
+        contest.push_contestant("bob");
+
+        let mut players = PlayersByName::new();
+        let system = EloMMR::default();
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 0);
+        let (num_logistic, normal_mu) = factors(&players["alice"].borrow());
+        assert_eq!(num_logistic, 1);
+
+        // A categorized contest keeps the general skill's logistic factors, and adds its
+        // evidence to the normal factor so that later updates build on it
+        contest.category = Some("latin".into());
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 1);
+        let (num_logistic, categorized_mu) = factors(&players["alice"].borrow());
+        assert_eq!(num_logistic, 1);
+        assert!(categorized_mu > normal_mu);
+
+        contest.category = None;
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 2);
+        assert_eq!(factors(&players["alice"].borrow()).0, 2);
+    }
+
+    #[test]
+    fn test_inactivity_decay() {
+        let policy = InactivityDecay {
+            limit: Rating {
+                mu: 1500.,
+                sig: 350.,
+            },
+            half_life_days: 365.,
+            grace_days: 30.,
+        };
+        let mut player = Player::with_rating(2500., 100., 0);
+
+        // A short absence changes nothing
+        player.delta_time = (20. * SECS_PER_DAY) as u64;
+        policy.apply(&mut player);
+        assert_eq!(player.approx_posterior.mu, 2500.);
+        assert_eq!(player.approx_posterior.sig, 100.);
+
+        // A long absence moves the rating halfway to the limit, and makes it less certain
+        player.delta_time = (395. * SECS_PER_DAY) as u64;
+        policy.apply(&mut player);
+        assert!((player.approx_posterior.mu - 2000.).abs() < 1e-9);
+        assert!(100. < player.approx_posterior.sig && player.approx_posterior.sig < 350.);
+    }
+
+    #[test]
+    fn test_individual_updates() {
+        let systems: Vec<Box<dyn RatingSystem>> = vec![
+            Box::new(EloMMR::default()),
+            Box::new(EloMMR::default_gaussian()),
+            Box::new(SimpleEloMMR::default()),
+            Box::new(Glicko::default()),
+            Box::new(BAR::default()),
+            Box::new(CodeforcesSys::default()),
+            Box::new(TopcoderSys::default()),
+        ];
+        for system in systems {
+            let mut player = Player::with_rating(1500., 200., 0);
+            push_event(&mut player, 0, 0, 0);
+            system.individual_update(ContestRatingParams::default(), &mut player, 2000.);
+
+            let mu = player.approx_posterior.mu;
+            assert!(1500. < mu && mu < 2000., "{:?} gave {}", system, mu);
+            assert_eq!(player.event_history[0].rating_mu, mu.round() as i32);
+        }
+    }
+
+    #[test]
+    fn test_unrated_entries() {
+        let mut players = PlayersByName::new();
+        let system = EloMMR::default();
+        let mut contest = Contest::new(0);
+        contest.push_contestant("bob");
+        contest.push_contestant("carol");
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 0);
+        let bob_before = players["bob"].borrow().clone();
+
+        // Beating an unrated player still counts, but only for the winner
+        let mut contest = Contest::new(1);
+        contest.push_contestant("alice");
+        contest.push_unrated("bob");
+        contest.push_unrated("dave");
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 1);
+
+        let bob = players["bob"].borrow();
+        assert_eq!(bob.event_history.len(), 1);
+        assert_eq!(bob.approx_posterior.mu, bob_before.approx_posterior.mu);
+        assert!(!players.contains_key("dave"));
+        assert!(players["alice"].borrow().approx_posterior.mu > 1500.);
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate handle")]
+    fn test_unrated_duplicate_before_rated() {
+        let mut contest = Contest::new(0);
+        contest.push_unrated("bob");
+        contest.push_contestant("alice");
+        contest.push_team("pair", vec!["bob".into(), "carol".into()]);
+        simulate_contest(
+            &mut PlayersByName::new(),
+            &contest,
+            &EloMMR::default(),
+            1500.,
+            350.,
+            0,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Duplicate handle")]
+    fn test_unrated_duplicate_after_rated() {
+        let mut contest = Contest::new(0);
+        contest.push_team("pair", vec!["bob".into(), "carol".into()]);
+        contest.push_contestant("alice");
+        contest.push_unrated("bob");
+        simulate_contest(
+            &mut PlayersByName::new(),
+            &contest,
+            &EloMMR::default(),
+            1500.,
+            350.,
+            0,
+        );
+    }
+
+    #[test]
+    fn test_newcomer_priors() {
+        let mut contest = Contest::new(0);
+        contest.rating_params.newcomer_prior = Some(Rating {
+            mu: 2500.,
+            sig: 200.,
+        });
+        contest.push_contestant("alice");
+        contest.push_contestant("bob");
+        contest.push_unrated("carol");
+
+        // Seeds take precedence over the contest's prior, which takes precedence over the default
+        let mut players = PlayersByName::new();
+        let system = CodeforcesSys::default();
+        let seed = |handle: &str| {
+            (handle == "bob").then_some(Rating {
+                mu: 1000.,
+                sig: 100.,
+            })
+        };
+        simulate_contest_seeded(&mut players, &contest, &system, 1500., 350., 0, &seed);
+        assert_eq!(players["alice"].borrow().approx_posterior.sig, 200.);
+        assert_eq!(players["bob"].borrow().approx_posterior.sig, 100.);
+        assert!(players["alice"].borrow().approx_posterior.mu > 2000.);
+        assert!(players["bob"].borrow().approx_posterior.mu < 1500.);
+    }
+}
@@ -1,8 +1,9 @@
-use super::{Rating, TanhTerm, robust_average};
+use super::{Rating, TanhTerm, robust_average_with_stats};
+use crate::numerical::NewtonResult;
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct PlayerEvent {
     pub contest_index: usize,
     pub rating_mu: i32,
@@ -28,6 +29,15 @@ pub struct Player {
     pub approx_posterior: Rating,
     pub update_time: u64,
     pub delta_time: u64,
+    /// The rate of random drift in this player's skill, for systems that estimate it per player
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volatility: Option<f64>,
+    /// This player's skill in each category, as an offset from their general skill
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub category_offsets: HashMap<String, Rating>,
+    /// The unrounded performance score from the latest update, which isn't saved
+    #[serde(skip)]
+    pub latest_perf: f64,
 }
 
 impl Player {
@@ -39,6 +49,31 @@ impl Player {
             approx_posterior: Rating { mu, sig },
             update_time,
             delta_time: 0,
+            volatility: None,
+            category_offsets: HashMap::new(),
+            latest_perf: 0.,
+        }
+    }
+
+    /// Creates a temporary player to represent a team with the given rating.
+    /// The team counts as being only as experienced as its least experienced member.
+    pub fn for_team(mu: f64, sig: f64, members: &[&Player]) -> Self {
+        let update_time = members.iter().map(|m| m.update_time).max().unwrap_or(0);
+        let mut team = Self::with_rating(mu, sig, update_time);
+        if let Some(newest) = members.iter().min_by_key(|m| m.times_played()) {
+            team.event_history = newest.event_history.clone();
+        }
+        team
+    }
+
+    /// The player's skill in the given category: their general skill plus its offset, if any.
+    pub fn rating_in(&self, category: Option<&str>) -> Rating {
+        match category.and_then(|c| self.category_offsets.get(c)) {
+            Some(offset) => Rating {
+                mu: self.approx_posterior.mu + offset.mu,
+                sig: self.approx_posterior.sig.hypot(offset.sig),
+            },
+            None => self.approx_posterior,
         }
     }
 
@@ -62,11 +97,29 @@ impl Player {
         assert_eq!(last_event.perf_score, 0);
 
         self.approx_posterior = rating;
+        self.latest_perf = performance_score;
         last_event.rating_mu = rating.mu.round() as i32;
         last_event.rating_sig = rating.sig.round() as i32;
         last_event.perf_score = performance_score.round() as i32;
     }
 
+    /// Like update_rating(), for updates that don't come from a single performance. The Gaussian
+    /// evidence that moves the rating from approx_posterior to the new one joins the normal
+    /// factor, so that later updates build on it without losing the logistic factors.
+    pub fn update_rating_and_absorb(&mut self, rating: Rating, performance_score: f64) {
+        let w_before = self.approx_posterior.sig.powi(-2);
+        let w_evidence = rating.sig.powi(-2) - w_before;
+        if w_evidence > 0. {
+            let mu_evidence = self.approx_posterior.mu
+                + (rating.mu - self.approx_posterior.mu) * (w_before + w_evidence) / w_evidence;
+            let wn = self.normal_factor.sig.powi(-2);
+            self.normal_factor.mu =
+                (wn * self.normal_factor.mu + w_evidence * mu_evidence) / (wn + w_evidence);
+            self.normal_factor.sig = (wn + w_evidence).recip().sqrt();
+        }
+        self.update_rating(rating, performance_score);
+    }
+
     pub fn update_rating_with_normal(&mut self, performance: Rating) {
         let wn = self.normal_factor.sig.powi(-2);
         let wp = performance.sig.powi(-2);
@@ -76,12 +129,17 @@ impl Player {
         let new_rating = if self.logistic_factors.is_empty() {
             self.normal_factor
         } else {
-            self.approximate_posterior(performance.sig)
+            self.approximate_posterior(performance.sig).0
         };
         self.update_rating(new_rating, performance.mu);
     }
 
-    pub fn update_rating_with_logistic(&mut self, performance: Rating, max_history: usize) {
+    // Returns diagnostics from the solver that computes the new rating
+    pub fn update_rating_with_logistic(
+        &mut self,
+        performance: Rating,
+        max_history: usize,
+    ) -> NewtonResult {
         if self.logistic_factors.len() >= max_history {
             // wl can be chosen so as to preserve total weight or rating; we choose the former.
             // Either way, the deleted element should be small enough not to matter.
@@ -93,23 +151,25 @@ impl Player {
         }
         self.logistic_factors.push_back(performance.into());
 
-        let new_rating = self.approximate_posterior(performance.sig);
+        let (new_rating, result) = self.approximate_posterior(performance.sig);
         self.update_rating(new_rating, performance.mu);
+        result
     }
 
     // Helper function that assumes the factors have been updated with the latest performance,
     // but self.approx_posterior has not yet been updated with this performance.
-    fn approximate_posterior(&self, perf_sig: f64) -> Rating {
+    fn approximate_posterior(&self, perf_sig: f64) -> (Rating, NewtonResult) {
         let normal_weight = self.normal_factor.sig.powi(-2);
-        let mu = robust_average(
+        let result = robust_average_with_stats(
             self.logistic_factors.iter().cloned(),
             -self.normal_factor.mu * normal_weight,
             normal_weight,
         );
+        let mu = result.root_or_warn();
         let sig = (self.approx_posterior.sig.powi(-2) + perf_sig.powi(-2))
             .recip()
             .sqrt();
-        Rating { mu, sig }
+        (Rating { mu, sig }, result)
     }
 
     // Method #1: the Gaussian/Brownian approximation, in which rating is a Markov state
@@ -120,6 +180,14 @@ impl Player {
         self.logistic_factors.clear();
     }
 
+    // Pulls the rating toward a limit, as in Rating::towards_noise(). Like method #1, this
+    // discards the logistic factors, since the old performances are no longer representative.
+    pub fn decay_and_collapse(&mut self, decay: f64, limit: Rating) {
+        self.approx_posterior = self.approx_posterior.towards_noise(decay, limit);
+        self.normal_factor = self.approx_posterior;
+        self.logistic_factors.clear();
+    }
+
     // Method #2: decrease weights without changing logistic sigmas
     // Equivalent to method #5 with transfer_speed == 0
     #[allow(dead_code)]
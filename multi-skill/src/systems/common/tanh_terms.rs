@@ -0,0 +1,321 @@
+use super::TanhTerm;
+use rayon::prelude::*;
+
+// Number of terms evaluated together; enough to fill the vector registers of most CPUs
+const LANES: usize = 8;
+
+// Beyond this argument, tanh() equals +-1 to within f64 precision
+const MAX_TANH_ARG: f64 = 40.;
+
+// Upper bounds on the 4th and 5th derivatives of tanh(), which govern the interpolation error
+const MAX_TANH_DERIV_4: f64 = 4.086;
+const MAX_TANH_DERIV_5: f64 = 16.;
+
+// A table covers the interval where every term's tanh() argument is within this range;
+// beyond it, the terms are summed directly
+const TABLE_MARGIN: f64 = 8.;
+
+// Branch-free exp(x) for |x| <= 2 * MAX_TANH_ARG, accurate to about one ulp. Unlike the standard
+// library's, it compiles to plain arithmetic, so that loops over it can be vectorized.
+#[inline(always)]
+fn exp_kernel(x: f64) -> f64 {
+    // Adding 1.5 * 2^52 rounds to the nearest integer, leaving it in the lowest mantissa bits
+    const SHIFTER: f64 = 6755399441055744.;
+    const LN_2_HI: f64 = 6.931471803691238e-1;
+    const LN_2_LO: f64 = 1.9082149292705877e-10;
+
+    // Reduce to exp(x) = 2^k * exp(r), where |r| <= ln(2) / 2
+    let shifted = x * std::f64::consts::LOG2_E + SHIFTER;
+    let k = shifted - SHIFTER;
+    let r = (x - k * LN_2_HI) - k * LN_2_LO;
+
+    // The Taylor series of exp(r), up to the r^13 term
+    let mut poly = 1. / 6227020800.;
+    for factorial in [
+        479001600., 39916800., 3628800., 362880., 40320., 5040., 720., 120., 24.,
+    ] {
+        poly = poly * r + 1. / factorial;
+    }
+    poly = ((poly * r + 1. / 6.) * r + 0.5) * r * r + r + 1.;
+
+    // Build 2^k directly from its exponent bits
+    let k_bits = shifted.to_bits().wrapping_sub(SHIFTER.to_bits());
+    poly * f64::from_bits(k_bits.wrapping_add(1023) << 52)
+}
+
+// LANES consecutive terms, with each of their fields stored contiguously
+#[derive(Clone, Copy, Debug, Default)]
+struct Block {
+    mu: [f64; LANES],
+    w_arg: [f64; LANES],
+    // multiplier * w_out
+    w_val: [f64; LANES],
+    // multiplier * w_out * w_arg
+    w_prime: [f64; LANES],
+}
+
+/// A batch of logistic terms in structure-of-arrays layout, each scaled by a multiplier.
+/// Their sum is evaluated several terms at a time, in a form the compiler can vectorize.
+#[derive(Clone, Debug, Default)]
+pub struct TanhTerms {
+    // The last block is padded with terms of zero weight, which contribute nothing
+    blocks: Vec<Block>,
+    len: usize,
+}
+
+impl TanhTerms {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            blocks: Vec::with_capacity(capacity.div_ceil(LANES)),
+            len: 0,
+        }
+    }
+
+    pub fn push(&mut self, term: TanhTerm, multiplier: f64) {
+        let lane = self.len % LANES;
+        if lane == 0 {
+            self.blocks.push(Block::default());
+        }
+        let block = self.blocks.last_mut().unwrap();
+        let w_val = multiplier * term.w_out;
+        block.mu[lane] = term.mu;
+        block.w_arg[lane] = term.w_arg;
+        block.w_val[lane] = w_val;
+        block.w_prime[lane] = w_val * term.w_arg;
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Iterates over the mu, w_arg, and w_val of each term
+    fn iter(&self) -> impl Iterator<Item = (f64, f64, f64)> + '_ {
+        self.blocks
+            .iter()
+            .flat_map(|block| {
+                (0..LANES).map(|lane| (block.mu[lane], block.w_arg[lane], block.w_val[lane]))
+            })
+            .take(self.len)
+    }
+
+    /// Equivalent to summing TanhTerm::base_values(x) over the terms, scaled by their multipliers
+    pub fn sum_base_values(&self, x: f64) -> (f64, f64) {
+        let (val, val_prime, _) = self.sum_with_derivs::<false>(x);
+        (val, val_prime)
+    }
+
+    // Same as sum_base_values(), but also computes the second derivative if CURVATURE is set
+    fn sum_with_derivs<const CURVATURE: bool>(&self, x: f64) -> (f64, f64, f64) {
+        // Accumulate each lane separately, so that a whole block is evaluated in parallel
+        let mut val = [0.; LANES];
+        let mut val_prime = [0.; LANES];
+        let mut val_prime2 = [0.; LANES];
+        for block in &self.blocks {
+            for lane in 0..LANES {
+                let z = (x - block.mu[lane]) * block.w_arg[lane];
+                let z = z.clamp(-MAX_TANH_ARG, MAX_TANH_ARG);
+                // tanh(z) = (e - 1) / (e + 1) and sech(z)^2 = 4e / (e + 1)^2, where e = exp(2z)
+                let e = exp_kernel(2. * z);
+                let recip = 1. / (e + 1.);
+                let tanh = (e - 1.) * recip;
+                let sech_sq = 4. * e * recip * recip;
+                val[lane] -= tanh * block.w_val[lane];
+                val_prime[lane] -= sech_sq * block.w_prime[lane];
+                if CURVATURE {
+                    val_prime2[lane] +=
+                        2. * tanh * sech_sq * block.w_prime[lane] * block.w_arg[lane];
+                }
+            }
+        }
+        (
+            val.iter().sum(),
+            val_prime.iter().sum(),
+            val_prime2.iter().sum(),
+        )
+    }
+}
+
+impl FromIterator<(TanhTerm, f64)> for TanhTerms {
+    fn from_iter<I: IntoIterator<Item = (TanhTerm, f64)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut terms = Self::with_capacity(iter.size_hint().0);
+        for (term, multiplier) in iter {
+            terms.push(term, multiplier);
+        }
+        terms
+    }
+}
+
+/// The sum of a batch of logistic terms, tabulated on a grid of points around them. Cubic
+/// Hermite interpolation then approximates TanhTerms::sum_base_values() in constant time, with
+/// errors in the value and derivative bounded by the tolerance, relative to the total w_val and
+/// w_prime of the terms respectively.
+#[derive(Clone, Debug)]
+pub struct TanhTable {
+    terms: TanhTerms,
+    lo: f64,
+    step: f64,
+    // the sum of the terms and its first two derivatives, at each grid point
+    grid: Vec<(f64, f64, f64)>,
+}
+
+impl TanhTable {
+    /// If the grid would have more points than there are terms, it's cheaper to sum the terms
+    /// directly, so the table is left empty.
+    pub fn new(terms: TanhTerms, tolerance: f64) -> Self {
+        let mut table = Self {
+            terms,
+            lo: 0.,
+            step: 1.,
+            grid: vec![],
+        };
+        if table.terms.is_empty() {
+            return table;
+        }
+
+        // Bound the interpolation errors of the value and derivative, whose Hermite interpolants
+        // have errors of at most step^4 / 384 times their 4th derivatives
+        let (mut sum_w, mut sum_w1, mut sum_w4, mut sum_w5) = (0., 0., 0., 0.);
+        let (mut mu_min, mut mu_max, mut w_arg_min) =
+            (f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY);
+        for (mu, w_arg, w_val) in table.terms.iter() {
+            let w_val = w_val.abs();
+            sum_w += w_val;
+            sum_w1 += w_val * w_arg;
+            sum_w4 += w_val * w_arg.powi(4);
+            sum_w5 += w_val * w_arg.powi(5);
+            mu_min = mu_min.min(mu);
+            mu_max = mu_max.max(mu);
+            w_arg_min = w_arg_min.min(w_arg);
+        }
+        let step_val = 384. * tolerance * sum_w / (MAX_TANH_DERIV_4 * sum_w4);
+        let step_prime = 384. * tolerance * sum_w1 / (MAX_TANH_DERIV_5 * sum_w5);
+        let step = step_val.min(step_prime).powf(0.25);
+
+        let margin = TABLE_MARGIN / w_arg_min;
+        let num_steps = ((mu_max - mu_min + 2. * margin) / step).ceil();
+        if num_steps.is_nan() || num_steps >= table.terms.len() as f64 {
+            return table;
+        }
+        table.lo = mu_min - margin;
+        table.step = step;
+        table.grid = (0..=num_steps as usize)
+            .into_par_iter()
+            .map(|i| {
+                table
+                    .terms
+                    .sum_with_derivs::<true>(table.lo + i as f64 * step)
+            })
+            .collect();
+        table
+    }
+
+    /// Whether the terms are summed directly, without a table
+    pub fn is_direct(&self) -> bool {
+        self.grid.is_empty()
+    }
+
+    /// Approximates TanhTerms::sum_base_values()
+    pub fn eval(&self, x: f64) -> (f64, f64) {
+        let pos = (x - self.lo) / self.step;
+        let i = pos.floor();
+        if !(i >= 0. && i + 1. < self.grid.len() as f64) {
+            return self.terms.sum_base_values(x);
+        }
+        let (v0, d0, c0) = self.grid[i as usize];
+        let (v1, d1, c1) = self.grid[i as usize + 1];
+
+        // Cubic Hermite basis functions on the unit interval
+        let t = pos - i;
+        let h00 = (1. + 2. * t) * (1. - t) * (1. - t);
+        let h10 = t * (1. - t) * (1. - t);
+        let h01 = t * t * (3. - 2. * t);
+        let h11 = t * t * (t - 1.);
+        let hermite = |p0: f64, m0: f64, p1: f64, m1: f64| {
+            h00 * p0 + h10 * self.step * m0 + h01 * p1 + h11 * self.step * m1
+        };
+        (hermite(v0, d0, v1, d1), hermite(d0, c0, d1, c1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exp_kernel() {
+        for i in -800..=800 {
+            let x = i as f64 * 0.1 + 0.0123;
+            let relative_error = (exp_kernel(x) - x.exp()).abs() / x.exp();
+            assert!(
+                relative_error < 1e-15,
+                "exp({}) is off by {}",
+                x,
+                relative_error
+            );
+        }
+    }
+
+    #[test]
+    fn test_matches_scalar() {
+        // Terms spread over a wide range of locations and widths, with some far from x
+        let terms: Vec<(TanhTerm, f64)> = (0..101)
+            .map(|i| {
+                let term = TanhTerm {
+                    mu: 1500. + 37. * (i as f64).powf(1.3) * (i as f64 * 2.1).sin(),
+                    w_arg: 0.004 + 0.003 * (i % 7) as f64,
+                    w_out: 0.01 + 0.002 * (i % 5) as f64,
+                };
+                (term, 1. + (i % 3) as f64)
+            })
+            .collect();
+        let batch: TanhTerms = terms.iter().copied().collect();
+        assert_eq!(batch.len(), terms.len());
+
+        for x in [-1e6, 0., 1000., 1499.9, 1500., 1723.4, 3000., 1e6] {
+            let (val, val_prime) = batch.sum_base_values(x);
+            let (expected, expected_prime) =
+                terms.iter().fold((0., 0.), |(s, sp), (term, multiplier)| {
+                    let (v, vp) = term.base_values(x);
+                    (s + multiplier * v, sp + multiplier * vp)
+                });
+            assert!((val - expected).abs() < 1e-14, "{} != {}", val, expected);
+            assert!((val_prime - expected_prime).abs() < 1e-16);
+        }
+    }
+
+    #[test]
+    fn test_table_error() {
+        let terms: TanhTerms = (0..3000)
+            .map(|i| {
+                let term = TanhTerm {
+                    mu: 1500. + 800. * (i as f64 * 0.61).sin(),
+                    w_arg: 0.002 + 0.001 * (i % 4) as f64,
+                    w_out: 0.004 + 0.002 * (i % 4) as f64,
+                };
+                (term, 1. + (i % 2) as f64)
+            })
+            .collect();
+        let (sum_w_val, sum_w_prime) = terms.iter().fold((0., 0.), |(s, sp), (_, w_arg, w_val)| {
+            (s + w_val, sp + w_val * w_arg)
+        });
+
+        for tolerance in [1e-5, 1e-8] {
+            let table = TanhTable::new(terms.clone(), tolerance);
+            assert!(!table.grid.is_empty());
+            // Check points between grid points, as well as beyond the table's range
+            for i in -100..=1100 {
+                let x = 1500. + 7.3 * i as f64 - 3700.;
+                let (val, val_prime) = table.eval(x);
+                let (expected, expected_prime) = terms.sum_base_values(x);
+                assert!((val - expected).abs() <= tolerance * sum_w_val);
+                assert!((val_prime - expected_prime).abs() <= tolerance * sum_w_prime);
+            }
+        }
+    }
+}
@@ -1,10 +1,20 @@
 //! Elo-R system details: https://arxiv.org/abs/2101.00400
-use super::{Player, Rating, RatingSystem, SECS_PER_DAY, TanhTerm};
+use super::{
+    InactivityDecay, Player, Rating, RatingSystem, SECS_PER_DAY, TanhTable, TanhTerm, TanhTerms,
+};
 use crate::data_processing::ContestRatingParams;
-use crate::numerical::{solve_newton, standard_normal_cdf, standard_normal_pdf};
+use crate::numerical::{
+    NewtonResult, SolverStats, solve_newton_from_with_stats, standard_normal_cdf,
+    standard_normal_pdf,
+};
 use core::ops::Range;
+use itertools::Either;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
+use serde::Deserialize;
 use std::cmp::Ordering;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
 use superslice::Ext;
 
@@ -82,41 +92,88 @@ impl Term for Rating {
     }
 }
 
-impl Term for TanhTerm {
-    fn eval(&self, x: f64, order: Ordering, split_ties: bool) -> (f64, f64) {
-        let (val, val_prime) = self.base_values(x);
-        match order {
-            Ordering::Less => (val - self.w_out, val_prime),
-            Ordering::Greater => (val + self.w_out, val_prime),
-            Ordering::Equal => {
-                if split_ties {
-                    (val, val_prime)
-                } else {
-                    (2. * val, 2. * val_prime)
-                }
+// The sums of a contest's logistic terms, tabulated so that each player's likelihood can be
+// evaluated in near-constant time, without subsampling
+struct LogisticTables {
+    all: TanhTable,
+    // each distinct rank, with the total w_out of the terms at that rank or better
+    ranks: Vec<(usize, f64)>,
+    // the terms at each distinct rank, which are counted a second time unless ties are split
+    ties: Vec<TanhTable>,
+}
+
+impl LogisticTables {
+    fn new(terms: &[(TanhTerm, SmallVec)], tolerance: f64, split_ties: bool) -> Self {
+        let all = terms
+            .iter()
+            .map(|(term, ranks)| (*term, ranks.len() as f64))
+            .collect();
+
+        // Split each term by rank, counting its multiplicity at that rank
+        let mut by_rank: Vec<(usize, TanhTerm, f64)> = terms
+            .iter()
+            .flat_map(|(term, ranks)| {
+                ranks
+                    .chunk_by(|a, b| a == b)
+                    .map(|equal| (equal[0], *term, equal.len() as f64))
+            })
+            .collect();
+        by_rank.sort_by_key(|&(lo, _, _)| lo);
+
+        let mut ranks = vec![];
+        let mut ties = vec![];
+        let mut cumulative_w_out = 0.;
+        for group in by_rank.chunk_by(|a, b| a.0 == b.0) {
+            cumulative_w_out += group
+                .iter()
+                .map(|&(_, term, count)| count * term.w_out)
+                .sum::<f64>();
+            ranks.push((group[0].0, cumulative_w_out));
+            if !split_ties {
+                let group_terms = group.iter().map(|&(_, term, count)| (term, count));
+                ties.push(TanhTable::new(group_terms.collect(), tolerance));
             }
         }
+        Self {
+            all: TanhTable::new(all, tolerance),
+            ranks,
+            ties,
+        }
     }
 
-    // Override to optimize this case where win, loss, and draw terms are similar.
-    fn evals(&self, x: f64, ranks: &[usize], my_rank: usize, split_ties: bool) -> (f64, f64) {
-        if ranks.len() == 1 {
-            // The unit-length case is very common, so we optimize it.
-            return self.eval(x, ranks[0].cmp(&my_rank), split_ties);
-        }
-        let (val, val_prime) = self.base_values(x);
-        let Range { start, end } = ranks.equal_range(&my_rank);
-        let mut total = ranks.len() as f64;
-        let win_minus_loss = total - (start + end) as f64;
-        if !split_ties {
-            let equal = end - start;
-            total += equal as f64;
+    // Same as summing the evals() of every term, for a player at the given rank
+    fn eval(&self, x: f64, my_rank: usize) -> (f64, f64) {
+        // Each win adds w_out to the sum of base values, and each loss subtracts it
+        let idx = self
+            .ranks
+            .binary_search_by_key(&my_rank, |&(lo, _)| lo)
+            .expect("Player's rank is missing from the terms");
+        let total = self.ranks.last().map_or(0., |&(_, w_out)| w_out);
+        let better = idx.checked_sub(1).map_or(0., |i| self.ranks[i].1);
+        let not_worse = self.ranks[idx].1;
+
+        let (mut value, mut deriv) = self.all.eval(x);
+        if let Some(ties) = self.ties.get(idx) {
+            let (v, p) = ties.eval(x);
+            value += v;
+            deriv += p;
         }
+        (value + (total - not_worse) - better, deriv)
+    }
+}
 
-        let value = val * total + self.w_out * win_minus_loss;
-        let deriv = val_prime * total;
-        (value, deriv)
+// Counts how many times a group of logistic terms at the given ranks should be added, and its
+// net number of wins minus losses, so that the group contributes
+// total * base_values(x) + win_minus_loss * w_out to the log-likelihood's derivative.
+fn tanh_multiplicity(ranks: &[usize], my_rank: usize, split_ties: bool) -> (f64, f64) {
+    let Range { start, end } = ranks.equal_range(&my_rank);
+    let mut total = ranks.len() as f64;
+    let win_minus_loss = total - (start + end) as f64;
+    if !split_ties {
+        let equal = end - start;
+        total += equal as f64;
     }
+    (total, win_minus_loss)
 }
 
 fn bucket(a: f64, width: f64) -> i32 {
@@ -134,9 +191,24 @@ fn cmp_by_bucket(a: f64, b: f64, width: f64) -> Ordering {
 #[derive(Debug)]
 pub enum EloMMRVariant {
     Gaussian,
+    // Gaussian, but with each performance's uncertainty given by the curvature of the
+    // log-likelihood at its maximum, instead of a fixed sig_perf
+    GaussianExact,
     Logistic(f64),
 }
 
+// How to choose the opponents whose terms approximate the likelihood
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SubsampleMode {
+    // the opponents rated closest to the player
+    #[default]
+    Window,
+    // one random opponent from each of several equal-sized rating strata, weighted by the
+    // stratum's size, so the whole rating range is represented without bias
+    Stratified,
+}
+
 #[derive(Debug)]
 pub struct EloMMR {
     // the weight of each new contest
@@ -150,12 +222,25 @@ pub struct EloMMR {
     pub drift_per_day: f64,
     // whether to count ties as half a win plus half a loss
     pub split_ties: bool,
-    // maximum number of opponents and recent events to use, as a compute-saving approximation
+    // maximum number of opponents to use, as a compute-saving approximation
     pub subsample_size: usize,
     // width of mu and sigma to group subsamples by
     pub subsample_bucket: f64,
+    // how to choose the subsample, when it's smaller than the contest
+    pub subsample_mode: SubsampleMode,
+    // maximum number of recent performances to keep in each player's logistic factor history,
+    // as a compute-saving approximation; must be at least 1
+    pub history_len: usize,
     // whether to use a Gaussian or logistic performance model
     pub variant: EloMMRVariant,
+    // if set, the logistic variant doesn't subsample, but sums over all terms by interpolating
+    // in tables; this bounds the error relative to the total weight of the terms
+    pub table_tolerance: Option<f64>,
+    // if set, contests with scores use the margins between them as extra evidence, treating
+    // each score-implied performance as a Gaussian observation with this standard deviation
+    pub margin_sig: Option<f64>,
+    // if set, players who return after a long absence are first pulled toward a limit rating
+    pub inactivity_decay: Option<InactivityDecay>,
 }
 
 impl Default for EloMMR {
@@ -169,6 +254,13 @@ impl EloMMR {
         Self::from_limit(0.2, 80., false, true, EloMMRVariant::Logistic(1.))
     }
 
+    pub fn default_tabulated() -> Self {
+        Self {
+            table_tolerance: Some(1e-9),
+            ..Self::default()
+        }
+    }
+
     pub fn default_gaussian() -> Self {
         Self::from_limit(0.2, 80., false, false, EloMMRVariant::Gaussian)
     }
@@ -191,6 +283,7 @@ impl EloMMR {
         let noob_delay = vec![];
         let subsample_size = if fast { 100 } else { usize::MAX };
         let subsample_bucket = if fast { 2. } else { 1e-5 };
+        let history_len = if fast { 100 } else { usize::MAX };
         Self {
             weight_limit,
             noob_delay,
@@ -199,11 +292,16 @@ impl EloMMR {
             split_ties,
             subsample_size,
             subsample_bucket,
+            subsample_mode: SubsampleMode::Window,
+            history_len,
             variant,
+            table_tolerance: None,
+            margin_sig: None,
+            inactivity_decay: None,
         }
     }
 
-    fn compute_weight(&self, mut contest_weight: f64, n: usize) -> f64 {
+    pub(crate) fn compute_weight(&self, mut contest_weight: f64, n: usize) -> f64 {
         contest_weight *= self.weight_limit;
         if let Some(delay_factor) = self.noob_delay.get(n) {
             contest_weight *= delay_factor;
@@ -217,19 +315,131 @@ impl EloMMR {
         (discrete_perf + continuous_perf).sqrt()
     }
 
-    fn compute_sig_drift(&self, weight: f64, delta_secs: f64) -> f64 {
+    pub(crate) fn compute_sig_drift(&self, weight: f64, delta_secs: f64) -> f64 {
         let discrete_drift = weight * self.sig_limit * self.sig_limit;
         let continuous_drift = self.drift_per_day * delta_secs / SECS_PER_DAY;
         (discrete_drift + continuous_drift).sqrt()
     }
 
+    // Adds the drift that accumulates between a player's contests
+    fn apply_drift(&self, player: &mut Player, weight: f64) {
+        if let Some(inactivity_decay) = &self.inactivity_decay {
+            inactivity_decay.apply(player);
+        }
+        let sig_drift = self.compute_sig_drift(weight, player.delta_time as f64);
+        match self.variant {
+            // if transfer_speed is infinite or the prior is Gaussian, the logistic
+            // weights become zero so this special-case optimization clears them out
+            EloMMRVariant::Logistic(transfer_speed) if transfer_speed < f64::INFINITY => {
+                player.add_noise_best(sig_drift, transfer_speed)
+            }
+            _ => player.add_noise_and_collapse(sig_drift),
+        }
+    }
+
+    // Incorporates a contest performance into a player's rating, after drift has been applied
+    // Returns diagnostics from the solver for the new rating, if the variant requires one
+    fn apply_performance(
+        &self,
+        params: ContestRatingParams,
+        player: &mut Player,
+        mu_perf: f64,
+        sig_perf: f64,
+    ) -> Option<NewtonResult> {
+        match self.variant {
+            EloMMRVariant::Gaussian | EloMMRVariant::GaussianExact => {
+                player.update_rating_with_normal(Rating {
+                    mu: mu_perf,
+                    sig: sig_perf,
+                });
+                None
+            }
+            EloMMRVariant::Logistic(_) => Some(player.update_rating_with_logistic(
+                Rating {
+                    mu: mu_perf.min(params.perf_ceiling),
+                    sig: sig_perf,
+                },
+                self.history_len,
+            )),
+        }
+    }
+
+    // Same as individual_update(), but reporting on the convergence of any solver it uses
+    fn individual_update_with_stats(
+        &self,
+        params: ContestRatingParams,
+        player: &mut Player,
+        mu_perf: f64,
+    ) -> SolverStats {
+        let weight = self.compute_weight(params.weight, player.times_played_excl());
+        let sig_perf = self.compute_sig_perf(weight);
+        self.apply_drift(player, weight);
+        let mut stats = SolverStats::default();
+        if let Some(result) = self.apply_performance(params, player, mu_perf, sig_perf) {
+            stats.record(&result);
+        }
+        stats
+    }
+
+    // Laplace's approximation treats the likelihood of the contest outcome as a Gaussian,
+    // whose variance is the reciprocal of the log-likelihood's negative curvature at its maximum.
+    // Many or closely matched rivals make the likelihood sharper, and hence the update tighter.
+    fn exact_sig_perf(sig_perf: f64, curvature: f64) -> f64 {
+        if curvature < 0. {
+            (-curvature).recip().sqrt()
+        } else {
+            sig_perf
+        }
+    }
+
+    // Maps each score onto the rating scale by matching the mean and spread of the scores to
+    // those of the corresponding performance distributions. Returns None where the score is
+    // unknown, or everywhere if the scores are too few or all equal to be informative.
+    fn score_implied_perfs(
+        perf_terms: &[(Rating, usize)],
+        scores: &[Option<f64>],
+    ) -> Vec<Option<f64>> {
+        let scored: Vec<(&Rating, f64)> = perf_terms
+            .iter()
+            .zip(scores)
+            .filter_map(|((rating, _), score)| score.map(|s| (rating, s)))
+            .collect();
+        if scored.len() < 2 {
+            return vec![None; scores.len()];
+        }
+        let n = scored.len() as f64;
+        let mean_score = scored.iter().map(|&(_, s)| s).sum::<f64>() / n;
+        let mean_mu = scored.iter().map(|&(r, _)| r.mu).sum::<f64>() / n;
+        let var_score = scored
+            .iter()
+            .map(|&(_, s)| (s - mean_score).powi(2))
+            .sum::<f64>()
+            / n;
+        let var_perf = scored
+            .iter()
+            .map(|&(r, _)| (r.mu - mean_mu).powi(2) + r.sig.powi(2))
+            .sum::<f64>()
+            / n;
+        if var_score <= 0. {
+            return vec![None; scores.len()];
+        }
+
+        let scale = (var_perf / var_score).sqrt();
+        scores
+            .iter()
+            .map(|score| score.map(|s| mean_mu + (s - mean_score) * scale))
+            .collect()
+    }
+
     fn subsample(
         terms: &[(Rating, SmallVec)],
         rating: f64,
         num_samples: usize,
         subsample_bucket: f64,
-    ) -> impl Iterator<Item = usize> + Clone {
-        // TODO: ensure the player still includes themself exactly once
+    ) -> Range<usize> {
+        // The window contains the player's own term at most once, but may miss it when more
+        // terms share its bucket than the window holds. Only SubsampleMode::Stratified
+        // guarantees to include it exactly once.
         let mut beg = terms
             .binary_search_by(|term| {
                 cmp_by_bucket(term.0.mu, rating, subsample_bucket).then(std::cmp::Ordering::Greater)
@@ -249,44 +459,124 @@ impl EloMMR {
         //.filter(move |&i| i != player_i)
         //.chain(Some(player_i))
     }
+
+    // Splits the terms other than the player's own into strata of consecutive ratings, and
+    // draws one term from each, weighted by the size of its stratum. Together with the player's
+    // own term, which is included exactly once, this makes num_samples terms in total.
+    fn subsample_stratified(
+        num_terms: usize,
+        own_term: usize,
+        num_samples: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<(usize, f64)> {
+        let num_others = num_terms - 1;
+        let num_strata = num_samples.saturating_sub(1).min(num_others);
+        let mut samples = Vec::with_capacity(num_strata + 1);
+        samples.push((own_term, 1.));
+        for stratum in 0..num_strata {
+            let beg = stratum * num_others / num_strata;
+            let end = (stratum + 1) * num_others / num_strata;
+            let other = rng.random_range(beg..end);
+            let idx = if other < own_term { other } else { other + 1 };
+            samples.push((idx, (end - beg) as f64));
+        }
+        samples
+    }
+
+    // Sums the weighted contributions of the terms to the log-likelihood's derivative,
+    // along with their contributions to its second derivative
+    fn sum_evals<'a>(
+        &self,
+        terms: impl Iterator<Item = (&'a (Rating, SmallVec), f64)>,
+        x: f64,
+        my_rank: usize,
+        init: (f64, f64),
+    ) -> (f64, f64) {
+        terms
+            .map(|((term, ranks), w)| {
+                let (v, vp) = term.evals(x, ranks, my_rank, self.split_ties);
+                (w * v, w * vp)
+            })
+            .fold(init, |(s, sp), (v, vp)| (s + v, sp + vp))
+    }
 }
 
 impl RatingSystem for EloMMR {
+    // Each member is credited with the team's performance, offset by their own deviation from
+    // the team's rating, as though they had competed individually.
+    fn infer_from_team(
+        &self,
+        params: ContestRatingParams,
+        team_before: &Player,
+        _team_after: &Player,
+        team_perf: f64,
+        member: &mut Player,
+    ) -> SolverStats {
+        let offset = member.approx_posterior.mu - team_before.approx_posterior.mu;
+        let mu_perf = team_perf + offset;
+        self.individual_update_with_stats(params, member, mu_perf)
+    }
+
+    fn individual_update(&self, params: ContestRatingParams, player: &mut Player, mu_perf: f64) {
+        self.individual_update_with_stats(params, player, mu_perf);
+    }
+
     fn round_update(
         &self,
         params: ContestRatingParams,
-        mut standings: Vec<(&mut Player, usize, usize)>,
+        standings: Vec<(&mut Player, usize, usize)>,
     ) {
+        self.round_update_with_scores(params, standings, &[]);
+    }
+
+    fn round_update_with_scores(
+        &self,
+        params: ContestRatingParams,
+        standings: Vec<(&mut Player, usize, usize)>,
+        scores: &[Option<f64>],
+    ) {
+        self.round_update_with_stats(params, standings, scores);
+    }
+
+    fn round_update_with_stats(
+        &self,
+        params: ContestRatingParams,
+        mut standings: Vec<(&mut Player, usize, usize)>,
+        scores: &[Option<f64>],
+    ) -> SolverStats {
         // Update ratings due to waiting period between contests,
         // then use it to create Gaussian terms for the Q-function.
         // The rank must also be stored in order to determine if it's a win, loss, or tie
         // term. filter_map can exclude the least useful terms from subsampling.
-        let mut base_terms: Vec<(Rating, usize)> = standings
+        let base_terms: Vec<(Rating, usize)> = standings
             .par_iter_mut()
             .map(|(player, lo, _)| {
                 let weight = self.compute_weight(params.weight, player.times_played_excl());
                 let sig_perf = self.compute_sig_perf(weight);
-                let sig_drift = self.compute_sig_drift(weight, player.delta_time as f64);
-                match self.variant {
-                    // if transfer_speed is infinite or the prior is Gaussian, the logistic
-                    // weights become zero so this special-case optimization clears them out
-                    EloMMRVariant::Logistic(transfer_speed) if transfer_speed < f64::INFINITY => {
-                        player.add_noise_best(sig_drift, transfer_speed)
-                    }
-                    _ => player.add_noise_and_collapse(sig_drift),
-                }
+                self.apply_drift(player, weight);
                 (player.approx_posterior.with_noise(sig_perf), *lo)
             })
             .collect();
 
+        // Translate scores into performances, to be used alongside the ranks
+        let implied_perfs = match self.margin_sig {
+            Some(_) if !scores.is_empty() => Self::score_implied_perfs(&base_terms, scores),
+            _ => vec![],
+        };
+
         // Sort terms by rating to allow for subsampling within a range or ratings.
-        base_terms.sort_unstable_by(|a, b| {
+        let mut order: Vec<usize> = (0..base_terms.len()).collect();
+        order.sort_unstable_by(|&a, &b| {
+            let (a, b) = (&base_terms[a], &base_terms[b]);
             cmp_by_bucket(a.0.mu, b.0.mu, self.subsample_bucket)
                 .then_with(|| cmp_by_bucket(a.0.sig, b.0.sig, self.subsample_bucket))
                 .then_with(|| a.1.cmp(&b.1))
         });
+        // Also record which term each player was merged into, so it can be kept in subsamples
+        let mut own_terms = vec![0; base_terms.len()];
         let mut normal_terms: Vec<(Rating, SmallVec)> = vec![];
-        for (term, lo) in base_terms {
+        for i in order {
+            let (term, lo) = base_terms[i];
             if let Some((last_term, ranks)) = normal_terms.last_mut() {
                 if same_bucket(last_term.mu, term.mu, self.subsample_bucket)
                     && same_bucket(last_term.sig, term.sig, self.subsample_bucket)
@@ -295,9 +585,11 @@ impl RatingSystem for EloMMR {
                     last_term.mu = (len * last_term.mu + term.mu) / (len + 1.);
                     last_term.sig = (len * last_term.sig + term.sig) / (len + 1.);
                     ranks.push(lo);
+                    own_terms[i] = normal_terms.len() - 1;
                     continue;
                 }
             }
+            own_terms[i] = normal_terms.len();
             normal_terms.push((term, smallvec::smallvec![lo]));
             //normal_terms.push((term, vec![lo]));
         }
@@ -311,15 +603,60 @@ impl RatingSystem for EloMMR {
         // Store the maximum subsample we've seen so far, to avoid logging excessive warnings
         let idx_len_max = AtomicUsize::new(9999);
 
-        // The computational bottleneck: update ratings based on contest performance
-        standings.into_par_iter().for_each(|(player, my_rank, _)| {
-            let player_mu = player.approx_posterior.mu;
-            let idx_subsample = Self::subsample(
-                &normal_terms,
-                player_mu,
-                self.subsample_size,
-                self.subsample_bucket,
-            );
+        // Pair each player with their score evidence, if any. It contributes a linear term to
+        // the likelihood's derivative. Logistic terms are scaled by a factor of two, so the
+        // same must be done here.
+        let margin_weight = match self.variant {
+            EloMMRVariant::Gaussian | EloMMRVariant::GaussianExact => 1.,
+            EloMMRVariant::Logistic(_) => 2.,
+        };
+        let standings: Vec<_> = standings
+            .into_iter()
+            .enumerate()
+            .map(|(i, (player, lo, _))| {
+                let margin = implied_perfs
+                    .get(i)
+                    .copied()
+                    .flatten()
+                    .zip(self.margin_sig)
+                    .map(|(perf, sig)| (perf, margin_weight / (sig * sig)));
+                (player, lo, margin, own_terms[i])
+            })
+            .collect();
+
+        // Tabulate all the terms at once, if the system is configured to skip subsampling
+        let tables = match self.variant {
+            EloMMRVariant::Logistic(_) => self
+                .table_tolerance
+                .map(|tolerance| LogisticTables::new(&tanh_terms, tolerance, self.split_ties)),
+            _ => None,
+        };
+        // Choose the terms that approximate a player's likelihood, along with their weights
+        let choose_subsample = |player_mu: f64, my_rank: usize, own_term: usize| {
+            let idx_subsample = match self.subsample_mode {
+                SubsampleMode::Window => Either::Left(
+                    Self::subsample(
+                        &normal_terms,
+                        player_mu,
+                        self.subsample_size,
+                        self.subsample_bucket,
+                    )
+                    .map(|i| (i, 1.)),
+                ),
+                SubsampleMode::Stratified => {
+                    // Seed from the player's own data, to be reproducible across thread schedules
+                    let seed = player_mu.to_bits() ^ my_rank as u64;
+                    Either::Right(
+                        Self::subsample_stratified(
+                            normal_terms.len(),
+                            own_term,
+                            self.subsample_size,
+                            &mut StdRng::seed_from_u64(seed),
+                        )
+                        .into_iter(),
+                    )
+                }
+            };
             // Log a warning if the subsample size is very large
             let idx_len_upper_bound = idx_subsample.size_hint().1.unwrap_or(usize::MAX);
             if idx_len_max.fetch_max(idx_len_upper_bound, Relaxed) < idx_len_upper_bound {
@@ -328,43 +665,359 @@ impl RatingSystem for EloMMR {
                     idx_len_upper_bound
                 );
             }
-            let bounds = (-6000.0, 9000.0);
-            let weight = self.compute_weight(params.weight, player.times_played_excl());
-            let sig_perf = self.compute_sig_perf(weight);
-
-            match self.variant {
-                EloMMRVariant::Gaussian => {
-                    let idx_subsample = idx_subsample.map(|i| &normal_terms[i]);
-                    let f = |x| {
-                        idx_subsample
-                            .clone()
-                            .map(|(rating, ranks)| rating.evals(x, ranks, my_rank, self.split_ties))
-                            .fold((0., 0.), |(s, sp), (v, vp)| (s + v, sp + vp))
-                    };
-                    let mu_perf = solve_newton(bounds, f);
-                    player.update_rating_with_normal(Rating {
-                        mu: mu_perf,
-                        sig: sig_perf,
-                    });
+            idx_subsample
+        };
+
+        // The computational bottleneck: update ratings based on contest performance
+        let stats = Mutex::new(SolverStats::default());
+        standings
+            .into_par_iter()
+            .for_each(|(player, my_rank, margin, own_term)| {
+                let player_mu = player.approx_posterior.mu;
+                let weight = self.compute_weight(params.weight, player.times_played_excl());
+                let sig_perf = self.compute_sig_perf(weight);
+                let init = |x: f64| margin.map_or((0., 0.), |(perf, w)| (w * (perf - x), -w));
+
+                let (result, sig_perf) = match self.variant {
+                    EloMMRVariant::Gaussian => {
+                        let idx_subsample = choose_subsample(player_mu, my_rank, own_term);
+                        let terms = idx_subsample.map(|(i, w)| (&normal_terms[i], w));
+                        let f = |x| self.sum_evals(terms.clone(), x, my_rank, init(x));
+                        (
+                            solve_newton_from_with_stats(player_mu, sig_perf, f),
+                            sig_perf,
+                        )
+                    }
+                    EloMMRVariant::GaussianExact => {
+                        let idx_subsample = choose_subsample(player_mu, my_rank, own_term);
+                        let terms = idx_subsample.map(|(i, w)| (&normal_terms[i], w));
+                        let f = |x| self.sum_evals(terms.clone(), x, my_rank, init(x));
+                        let result = solve_newton_from_with_stats(player_mu, sig_perf, f);
+                        let (_, curvature) = f(result.root);
+                        (result, Self::exact_sig_perf(sig_perf, curvature))
+                    }
+                    EloMMRVariant::Logistic(_) if tables.is_some() => {
+                        let tables = tables.as_ref().unwrap();
+                        let f = |x| {
+                            let (s, sp) = init(x);
+                            let (v, vp) = tables.eval(x, my_rank);
+                            (s + v, sp + vp)
+                        };
+                        (
+                            solve_newton_from_with_stats(player_mu, sig_perf, f),
+                            sig_perf,
+                        )
+                    }
+                    EloMMRVariant::Logistic(_) => {
+                        let idx_subsample = choose_subsample(player_mu, my_rank, own_term);
+                        // Win and loss terms differ only by a constant, which is summed up front,
+                        // leaving a batch of tanh terms to evaluate at each Newton iteration
+                        let mut offset = 0.;
+                        let batch: TanhTerms = idx_subsample
+                            .map(|(i, w)| {
+                                let (term, ranks) = &tanh_terms[i];
+                                let (total, win_minus_loss) =
+                                    tanh_multiplicity(ranks, my_rank, self.split_ties);
+                                offset += w * win_minus_loss * term.w_out;
+                                (*term, w * total)
+                            })
+                            .collect();
+                        let f = |x| {
+                            let (s, sp) = init(x);
+                            let (v, vp) = batch.sum_base_values(x);
+                            (s + offset + v, sp + vp)
+                        };
+                        (
+                            solve_newton_from_with_stats(player_mu, sig_perf, f),
+                            sig_perf,
+                        )
+                    }
+                };
+                let mut player_stats = SolverStats::default();
+                player_stats.record(&result);
+                let mu_perf = result.root_or_warn();
+                if let Some(result) = self.apply_performance(params, player, mu_perf, sig_perf) {
+                    player_stats.record(&result);
                 }
-                EloMMRVariant::Logistic(_) => {
-                    let idx_subsample = idx_subsample.map(|i| &tanh_terms[i]);
-                    let f = |x| {
-                        idx_subsample
-                            .clone()
-                            .map(|(rating, ranks)| rating.evals(x, ranks, my_rank, self.split_ties))
-                            .fold((0., 0.), |(s, sp), (v, vp)| (s + v, sp + vp))
-                    };
-                    let mu_perf = solve_newton(bounds, f).min(params.perf_ceiling);
-                    player.update_rating_with_logistic(
-                        Rating {
-                            mu: mu_perf,
-                            sig: sig_perf,
-                        },
-                        self.subsample_size, // TODO: make separate history length parameter
-                    );
+                *stats.lock().unwrap() += player_stats;
+            });
+        stats.into_inner().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_processing::Contest;
+    use crate::systems::{
+        CodeforcesSys, PlayersByName, SimpleEloMMR, simulate_contest, simulate_contest_seeded,
+    };
+
+    #[test]
+    fn test_score_margins() {
+        let mut contest = Contest::new(0);
+        for handle in ["alice", "bob", "carol", "dave"] {
+            contest.push_contestant(handle);
+        }
+        let margin_system = EloMMR {
+            margin_sig: Some(200.),
+            ..Default::default()
+        };
+        let rate = |system: &EloMMR, contest: &Contest| {
+            let mut players = PlayersByName::new();
+            simulate_contest(&mut players, contest, system, 1500., 350., 0);
+            players["alice"].borrow().approx_posterior.mu
+        };
+
+        // Without scores, the margin parameter has no effect
+        let baseline = rate(&EloMMR::default(), &contest);
+        assert_eq!(rate(&margin_system, &contest), baseline);
+
+        // Winning by a large margin is rewarded more than winning by a small one
+        contest.scores = [("alice", 100.), ("bob", 99.), ("carol", 98.), ("dave", 97.)]
+            .into_iter()
+            .map(|(handle, score)| (handle.to_owned(), score))
+            .collect();
+        let narrow = rate(&margin_system, &contest);
+        contest.scores.insert("alice".to_owned(), 200.);
+        let wide = rate(&margin_system, &contest);
+        assert!(baseline > 1500.);
+        assert!(wide > narrow);
+    }
+
+    #[test]
+    fn test_solver_stats() {
+        let mut contest = Contest::new(0);
+        for handle in ["alice", "bob", "carol", "dave"] {
+            contest.push_contestant(handle);
+        }
+        let mut players = PlayersByName::new();
+        let system = EloMMR::default();
+        let stats =
+            simulate_contest_seeded(&mut players, &contest, &system, 1500., 350., 0, &|_| None);
+
+        // The logistic variant solves once for each performance, and once for each new rating
+        assert_eq!(stats.solves, 8);
+        assert_eq!(stats.failures, 0);
+        assert!(stats.max_iterations > 0);
+        assert!(stats.max_residual <= 1e-10);
+
+        // Team members solve once more each, for their own new ratings
+        let mut team_contest = Contest::new(1);
+        team_contest.push_team("team", vec!["erin".to_owned(), "frank".to_owned()]);
+        team_contest.push_contestant("alice");
+        let stats = simulate_contest_seeded(
+            &mut players,
+            &team_contest,
+            &system,
+            1500.,
+            350.,
+            1,
+            &|_| None,
+        );
+        assert_eq!(stats.solves, 6);
+
+        // Other systems built on Newton's method report on it too
+        let simple_stats = simulate_contest_seeded(
+            &mut PlayersByName::new(),
+            &contest,
+            &SimpleEloMMR::default(),
+            1500.,
+            350.,
+            0,
+            &|_| None,
+        );
+        assert_eq!(simple_stats.solves, 8);
+        let codeforces_stats = simulate_contest_seeded(
+            &mut PlayersByName::new(),
+            &contest,
+            &CodeforcesSys::default(),
+            1500.,
+            350.,
+            0,
+            &|_| None,
+        );
+        assert_eq!(codeforces_stats.solves, 4);
+        assert_eq!(codeforces_stats.failures, 0);
+    }
+
+    #[test]
+    fn test_gaussian_exact() {
+        // Alice, rated 1500, wins against rivals with the given ratings
+        let winner_sig = |variant: EloMMRVariant, rival_mus: &[f64]| {
+            let mut contest = Contest::new(0);
+            contest.push_contestant("alice");
+            for i in 0..rival_mus.len() {
+                contest.push_contestant(format!("rival{}", i));
+            }
+            let system = EloMMR {
+                variant,
+                ..EloMMR::default_gaussian()
+            };
+            let seed = |handle: &str| {
+                let index: usize = handle.strip_prefix("rival")?.parse().ok()?;
+                Some(Rating {
+                    mu: rival_mus[index],
+                    sig: 350.,
+                })
+            };
+            let mut players = PlayersByName::new();
+            simulate_contest_seeded(&mut players, &contest, &system, 1500., 350., 0, &seed);
+            players["alice"].borrow().approx_posterior.sig
+        };
+        let exact = |rival_mus: &[f64]| winner_sig(EloMMRVariant::GaussianExact, rival_mus);
+        let fixed = |rival_mus: &[f64]| winner_sig(EloMMRVariant::Gaussian, rival_mus);
+
+        // Close or numerous rivals pin down the performance, while a fixed variance ignores them
+        assert!(exact(&[1500.]) < exact(&[700.]));
+        assert!(exact(&[1500.; 30]) < exact(&[1500.]));
+        assert!(exact(&[1500.; 30]) < fixed(&[1500.; 30]));
+        assert_eq!(fixed(&[1500.]), fixed(&[700.]));
+    }
+
+    #[test]
+    fn test_stratified_subsample() {
+        let handles: Vec<String> = (0..60).map(|i| format!("player{}", i)).collect();
+        // Multipliers coprime to 60 reorder the players differently in each contest
+        let contests: Vec<Contest> = [1, 7, 13]
+            .into_iter()
+            .enumerate()
+            .map(|(index, multiplier)| {
+                let mut contest = Contest::new(index);
+                for i in 0..handles.len() {
+                    contest.push_contestant(&handles[(i * multiplier + index) % 60]);
                 }
+                contest
+            })
+            .collect();
+        let rate = |system: &EloMMR| {
+            let mut players = PlayersByName::new();
+            for (index, contest) in contests.iter().enumerate() {
+                simulate_contest(&mut players, contest, system, 1500., 350., index);
+            }
+            handles
+                .iter()
+                .map(|handle| players[handle].borrow().approx_posterior.mu)
+                .collect::<Vec<f64>>()
+        };
+        let full = rate(&EloMMR::default());
+        let error = |subsample_size: usize| {
+            let system = EloMMR {
+                subsample_size,
+                subsample_mode: SubsampleMode::Stratified,
+                ..Default::default()
             };
-        });
+            let sum_sq: f64 = rate(&system)
+                .iter()
+                .zip(&full)
+                .map(|(mu, full_mu)| (mu - full_mu).powi(2))
+                .sum();
+            (sum_sq / full.len() as f64).sqrt()
+        };
+
+        // The approximation improves with more samples, until every term is its own stratum
+        let errors = [4, 15, 45, 60].map(error);
+        assert!(errors.windows(2).all(|w| w[0] > w[1]), "{:?}", errors);
+        assert!(errors[3] < 1e-9, "{:?}", errors);
+    }
+
+    #[test]
+    fn test_tabulated() {
+        // Enough distinct terms for the tables to be smaller than the batches, with some ties
+        let terms: Vec<(TanhTerm, SmallVec)> = (0..3000)
+            .map(|i| {
+                let rating = Rating {
+                    mu: 1500. + 600. * (i as f64 * 0.77).sin(),
+                    sig: 200. + (i % 50) as f64,
+                };
+                let ranks = if i % 10 == 0 {
+                    smallvec::smallvec![i, i, i + 1]
+                } else {
+                    smallvec::smallvec![i / 4]
+                };
+                (rating.into(), ranks)
+            })
+            .collect();
+
+        for split_ties in [false, true] {
+            let tables = LogisticTables::new(&terms, 1e-9, split_ties);
+            assert!(tables.ties.iter().all(|table| table.is_direct()) && !tables.all.is_direct());
+            for my_rank in [0, 1, 10, 11, 749, 2990] {
+                for x in [0., 900., 1234.5, 1500., 2100., 5000.] {
+                    let (value, deriv) = tables.eval(x, my_rank);
+                    let (expected, expected_deriv) = terms
+                        .iter()
+                        .map(|(term, ranks)| {
+                            let (total, win_minus_loss) =
+                                tanh_multiplicity(ranks, my_rank, split_ties);
+                            let (v, p) = term.base_values(x);
+                            (total * v + win_minus_loss * term.w_out, total * p)
+                        })
+                        .fold((0., 0.), |(s, sp), (v, p)| (s + v, sp + p));
+                    assert!((value - expected).abs() < 1e-9, "{} != {}", value, expected);
+                    assert!((deriv - expected_deriv).abs() < 1e-11);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_scale_equivariance() {
+        let handles = ["alice", "bob", "carol", "dave", "eve"];
+        let mut contests: Vec<Contest> = (0..4)
+            .map(|index| {
+                let mut contest = Contest::new(index);
+                for i in 0..handles.len() {
+                    contest.push_contestant(handles[(i * (index + 1) + index) % handles.len()]);
+                }
+                contest
+            })
+            .collect();
+        // Team members infer their updates from their team's performance
+        let mut contest = Contest::new(4);
+        contest.push_team("red", vec!["carol".into(), "alice".into()]);
+        contest.push_contestant("eve");
+        contest.push_team("blue", vec!["bob".into(), "dave".into()]);
+        contests.push(contest);
+        // Rescales every parameter that's expressed in rating units
+        let systems = |scale: f64| -> Vec<Box<dyn RatingSystem>> {
+            let rescale = |system: EloMMR| EloMMR {
+                sig_limit: system.sig_limit * scale,
+                subsample_bucket: system.subsample_bucket * scale,
+                ..system
+            };
+            vec![
+                Box::new(rescale(EloMMR::default())),
+                Box::new(rescale(EloMMR::default_gaussian())),
+                Box::new(SimpleEloMMR {
+                    sig_limit: 80. * scale,
+                    ..Default::default()
+                }),
+            ]
+        };
+        let rate = |system: &dyn RatingSystem, mu_noob: f64, sig_noob: f64| {
+            let mut players = PlayersByName::new();
+            for (index, contest) in contests.iter().enumerate() {
+                simulate_contest(&mut players, contest, system, mu_noob, sig_noob, index);
+            }
+            handles.map(|handle| players[handle].borrow().approx_posterior)
+        };
+
+        // Ratings on a different scale, far outside the usual range, are an affine map
+        // of the usual ratings
+        for (scale, mu_noob) in [(1. / 60., 25.), (100., -1e5)] {
+            let usual = systems(1.);
+            let rescaled = systems(scale);
+            for (usual, rescaled) in usual.iter().zip(&rescaled) {
+                let expected = rate(usual.as_ref(), 1500., 350.);
+                let actual = rate(rescaled.as_ref(), mu_noob, 350. * scale);
+                for (expected, actual) in expected.iter().zip(&actual) {
+                    let tolerance = 1e-6 * scale;
+                    let mu = mu_noob + scale * (expected.mu - 1500.);
+                    assert!((actual.mu - mu).abs() < tolerance, "{:?}", rescaled);
+                    assert!((actual.sig - scale * expected.sig).abs() < tolerance);
+                }
+            }
+        }
     }
 }
@@ -33,22 +33,74 @@ impl RatingSystem for EndureElo {
         params: ContestRatingParams,
         mut standings: Vec<(&mut Player, usize, usize)>,
     ) {
-        unimplemented!("The EndureElo system has only skeleton code!");
-
-        standings.par_iter_mut().for_each(|(player, lo, _)| {
-            player.add_noise_and_collapse(self.sig_drift);
-        });
+        let sig_perf = self.beta / params.weight.sqrt();
+        let all_ratings: Vec<(Rating, usize)> = standings
+            .par_iter_mut()
+            .map(|(player, lo, _)| {
+                player.add_noise_and_collapse(self.sig_drift);
+                (player.approx_posterior, *lo)
+            })
+            .collect();
 
         standings.into_par_iter().for_each(|(player, my_lo, _)| {
             let my_rating = &player.approx_posterior;
-            let probability = 0.5;
-            let info = probability * (1.0 - probability);
+            let mut info = 0.;
+            let mut update = 0.;
+            for (rating, lo) in &all_ratings {
+                let outcome = match my_lo.cmp(lo) {
+                    std::cmp::Ordering::Less => 1.,
+                    std::cmp::Ordering::Equal => 0.5,
+                    std::cmp::Ordering::Greater => 0.,
+                };
+                let probability = self.win_probability(sig_perf, my_rating, rating);
+                // Derivative of the logistic's argument with respect to the player's rating
+                let slope = TANH_MULTIPLIER / rating.sig.hypot(sig_perf);
+
+                info += slope * slope * probability * (1. - probability);
+                update += slope * (outcome - probability);
+            }
+            // The loop above counted the player against themself: a draw that's uninformative
+            // for the mean, but which must be removed from the information sum
+            let self_slope = TANH_MULTIPLIER / my_rating.sig.hypot(sig_perf);
+            info -= 0.25 * self_slope * self_slope;
+
+            // Compute new rating deviation
             let sig = (my_rating.sig.powi(-2) + info).recip().sqrt();
 
             // Compute new rating
-            let mu = my_rating.mu; // + update;
+            let mu = my_rating.mu + sig * sig * update;
 
             player.update_rating(Rating { mu, sig }, 0.);
         });
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_processing::Contest;
+    use crate::systems::{PlayersByName, simulate_contest};
+
+    #[test]
+    fn test_symmetric_contest() {
+        let mut contest = Contest::new(0);
+        contest.push_contestant("winner");
+        contest.push_contestant("loser");
+        let mut players = PlayersByName::new();
+        simulate_contest(
+            &mut players,
+            &contest,
+            &EndureElo::default(),
+            1500.,
+            350.,
+            0,
+        );
+
+        let winner = players["winner"].borrow().approx_posterior;
+        let loser = players["loser"].borrow().approx_posterior;
+        assert!(winner.mu > 1500.);
+        assert!((winner.mu - 1500. + loser.mu - 1500.).abs() < 1e-9);
+        assert!((winner.sig - loser.sig).abs() < 1e-9);
+        assert!(winner.sig < 350.);
+    }
+}
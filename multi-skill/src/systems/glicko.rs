@@ -28,6 +28,21 @@ impl Glicko {
 }
 
 impl RatingSystem for Glicko {
+    // The performance is treated like the outcome of one highly informative match,
+    // as in round_update()
+    fn individual_update(&self, params: ContestRatingParams, player: &mut Player, mu_perf: f64) {
+        let sig_perf = self.beta / params.weight.sqrt();
+        let gli_q = TANH_MULTIPLIER / sig_perf;
+        player.add_noise_and_collapse(self.sig_drift);
+
+        let my_rating = &player.approx_posterior;
+        let info = 0.25 * gli_q * gli_q;
+        let sig = (my_rating.sig.powi(-2) + info).recip().sqrt();
+        let mu = my_rating.mu + sig * sig * info * (mu_perf - my_rating.mu);
+
+        player.update_rating(Rating { mu, sig }, mu_perf);
+    }
+
     fn round_update(
         &self,
         params: ContestRatingParams,
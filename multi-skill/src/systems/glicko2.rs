@@ -0,0 +1,164 @@
+//! Glicko-2 system details: http://www.glicko.net/glicko/glicko2.pdf
+
+use super::{Player, Rating, RatingSystem};
+use crate::data_processing::ContestRatingParams;
+use crate::numerical::{TANH_MULTIPLIER, solve_illinois};
+use rayon::prelude::*;
+
+#[derive(Debug)]
+pub struct Glicko2 {
+    // the logistic performance scale, matching Glicko's beta
+    pub beta: f64,
+    // constrains the change in volatility over time, typically between 0.3 and 1.2
+    pub tau: f64,
+    // initial volatility of a new player, on the Glicko-2 scale
+    pub vol_noob: f64,
+}
+
+impl Default for Glicko2 {
+    fn default() -> Self {
+        Self {
+            beta: 400. * TANH_MULTIPLIER / std::f64::consts::LN_10,
+            tau: 0.5,
+            vol_noob: 0.06,
+        }
+    }
+}
+
+impl Glicko2 {
+    // The factor by which ratings are divided to convert them to the Glicko-2 scale
+    fn scale(&self) -> f64 {
+        self.beta / TANH_MULTIPLIER
+    }
+
+    fn g(phi: f64) -> f64 {
+        (1. + 3. * phi * phi / std::f64::consts::PI.powi(2))
+            .sqrt()
+            .recip()
+    }
+
+    // Step 5 of the paper: the new volatility after a rating period with the given
+    // estimated variance v and improvement delta, both on the Glicko-2 scale
+    fn compute_volatility(&self, phi: f64, vol: f64, v: f64, delta: f64) -> f64 {
+        let a = (vol * vol).ln();
+        let tau_sq = self.tau * self.tau;
+        let f = |x: f64| {
+            let ex = x.exp();
+            let denom = phi * phi + v + ex;
+            ex * (delta * delta - phi * phi - v - ex) / (2. * denom * denom) - (x - a) / tau_sq
+        };
+
+        let b = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.;
+            while f(a - k * self.tau) < 0. {
+                k += 1.;
+            }
+            a - k * self.tau
+        };
+        let x = solve_illinois((a.min(b), a.max(b)), f);
+        (0.5 * x).exp()
+    }
+}
+
+impl RatingSystem for Glicko2 {
+    fn round_update(
+        &self,
+        params: ContestRatingParams,
+        standings: Vec<(&mut Player, usize, usize)>,
+    ) {
+        // Convert everyone to the Glicko-2 scale
+        let scale = self.scale();
+        let all_ratings: Vec<(f64, f64, usize)> = standings
+            .par_iter()
+            .map(|(player, lo, _)| {
+                let rating = player.approx_posterior;
+                (rating.mu / scale, Self::g(rating.sig / scale), *lo)
+            })
+            .collect();
+
+        standings
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(i, (player, my_lo, _))| {
+                let mu = player.approx_posterior.mu / scale;
+                let phi = player.approx_posterior.sig / scale;
+                let vol = player.volatility.unwrap_or(self.vol_noob);
+
+                // Treat the round as a set of pairwise games against every other participant
+                let mut v_inv = 0.;
+                let mut improvement = 0.;
+                for (j, &(foe_mu, foe_g, lo)) in all_ratings.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    let outcome = match my_lo.cmp(&lo) {
+                        std::cmp::Ordering::Less => 1.,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Greater => 0.,
+                    };
+                    let expected = (1. + (-foe_g * (mu - foe_mu)).exp()).recip();
+                    v_inv += params.weight * foe_g * foe_g * expected * (1. - expected);
+                    improvement += params.weight * foe_g * (outcome - expected);
+                }
+                let v = v_inv.recip();
+                let delta = v * improvement;
+
+                // Update the volatility, then use it to inflate the rating deviation
+                let new_vol = self.compute_volatility(phi, vol, v, delta);
+                let phi_star = phi.hypot(new_vol);
+
+                // Compute the new rating and deviation
+                let new_phi = (phi_star.powi(-2) + v_inv).recip().sqrt();
+                let new_mu = mu + new_phi * new_phi * improvement;
+
+                player.volatility = Some(new_vol);
+                player.update_rating(
+                    Rating {
+                        mu: new_mu * scale,
+                        sig: new_phi * scale,
+                    },
+                    0.,
+                );
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::systems::PlayerEvent;
+
+    #[test]
+    fn test_glickman_example() {
+        let new_player = |mu, sig| {
+            let mut player = Player::with_rating(mu, sig, 0);
+            player.event_history.push(PlayerEvent {
+                contest_index: 0,
+                rating_mu: 0,
+                rating_sig: 0,
+                perf_score: 0,
+                place: 0,
+            });
+            player
+        };
+        let mut player = new_player(1500., 200.);
+        let mut foe1 = new_player(1400., 30.);
+        let mut foe2 = new_player(1550., 100.);
+        let mut foe3 = new_player(1700., 300.);
+
+        // The player beats foe1 and loses to foe2 and foe3, as in the paper's example
+        let standings = vec![
+            (&mut foe3, 0, 0),
+            (&mut foe2, 1, 1),
+            (&mut player, 2, 2),
+            (&mut foe1, 3, 3),
+        ];
+        Glicko2::default().round_update(ContestRatingParams::default(), standings);
+
+        assert!((player.approx_posterior.mu - 1464.06).abs() < 0.01);
+        assert!((player.approx_posterior.sig - 151.52).abs() < 0.01);
+        assert!((player.volatility.unwrap() - 0.05999).abs() < 1e-5);
+    }
+}
@@ -4,24 +4,34 @@ mod common;
 mod elo_mmr;
 mod endure_elo;
 mod glicko;
+mod glicko2;
+mod plackett_luce;
 mod simple_elo_mmr;
+mod thurstone_mosteller;
 mod topcoder_sys;
 mod true_skill;
 
 pub use bar::BAR;
 pub use codeforces_sys::CodeforcesSys;
 pub use common::{
-    Player, PlayerEvent, PlayersByName, Rating, RatingSystem, TanhTerm, get_participant_ratings,
-    outcome_free, robust_average, simulate_contest,
+    InactivityDecay, Player, PlayerEvent, PlayersByName, Rating, RatingSystem, TanhTable, TanhTerm,
+    TanhTerms, get_participant_ratings, outcome_free, robust_average, robust_average_with_stats,
+    simulate_contest, simulate_contest_seeded,
 };
-pub use elo_mmr::{EloMMR, EloMMRVariant};
+pub use elo_mmr::{EloMMR, EloMMRVariant, SubsampleMode};
 pub use endure_elo::EndureElo;
 pub use glicko::Glicko;
+pub use glicko2::Glicko2;
+pub use plackett_luce::PlackettLuce;
 pub use simple_elo_mmr::SimpleEloMMR;
+pub use thurstone_mosteller::ThurstoneMosteller;
 pub use topcoder_sys::TopcoderSys;
 pub use true_skill::TrueSkillSPb;
 
 pub static SECS_PER_DAY: f64 = 86_400.;
+/// The initial uncertainty in a player's offset from their general skill, upon first
+/// competing in a new category
+pub static SIG_CATEGORY_NOOB: f64 = 100.;
 
 // TODO: add a version that can take parameters, like in experiment_config but polymorphic
 pub fn get_rating_system_by_name(
@@ -29,7 +39,10 @@ pub fn get_rating_system_by_name(
 ) -> Result<Box<dyn RatingSystem + Send>, String> {
     match system_name {
         "bar" => Ok(Box::new(BAR::default())),
+        "bar-pl" => Ok(Box::new(PlackettLuce::default())),
+        "bar-tm" => Ok(Box::new(ThurstoneMosteller::default())),
         "glicko" => Ok(Box::new(Glicko::default())),
+        "glicko2" => Ok(Box::new(Glicko2::default())),
         "endure" => Ok(Box::new(EndureElo::default())),
         "cfsys" => Ok(Box::new(CodeforcesSys::default())),
         "tcsys" => Ok(Box::new(TopcoderSys::default())),
@@ -38,9 +51,10 @@ pub fn get_rating_system_by_name(
         "mmx-fast" => Ok(Box::new(EloMMR::default_gaussian_fast())),
         "mmr" => Ok(Box::new(EloMMR::default())),
         "mmr-fast" => Ok(Box::new(EloMMR::default_fast())),
+        "mmr-table" => Ok(Box::new(EloMMR::default_tabulated())),
         "mmr-simple" => Ok(Box::new(SimpleEloMMR::default())),
         name => Err(format!(
-            "{} is not a valid rating system. Must be one of: bar, glicko, cfsys, tcsys, trueskill, mmx, mmx-fast, mmr, mmr-fast, mmr-simple",
+            "{} is not a valid rating system. Must be one of: bar, bar-pl, bar-tm, glicko, glicko2, endure, cfsys, tcsys, trueskill, mmx, mmx-fast, mmr, mmr-fast, mmr-table, mmr-simple",
             name
         )),
     }
@@ -0,0 +1,138 @@
+//! The Plackett-Luce model, or Algorithm 2 (PL)
+//! from https://jmlr.csail.mit.edu/papers/volume12/weng11a/weng11a.pdf
+
+use super::{Player, Rating, RatingSystem};
+use crate::data_processing::ContestRatingParams;
+use crate::numerical::TANH_MULTIPLIER;
+use rayon::prelude::*;
+
+#[derive(Debug)]
+pub struct PlackettLuce {
+    pub beta: f64,
+    pub sig_drift: f64,
+    pub kappa: f64,
+}
+
+impl Default for PlackettLuce {
+    fn default() -> Self {
+        Self {
+            beta: 400. * TANH_MULTIPLIER / std::f64::consts::LN_10,
+            sig_drift: 35.,
+            kappa: 1e-4,
+        }
+    }
+}
+
+impl RatingSystem for PlackettLuce {
+    fn round_update(
+        &self,
+        params: ContestRatingParams,
+        mut standings: Vec<(&mut Player, usize, usize)>,
+    ) {
+        let all_ratings: Vec<(Rating, usize)> = standings
+            .par_iter_mut()
+            .map(|(player, lo, _)| {
+                player.add_noise_and_collapse(self.sig_drift);
+                (player.approx_posterior, *lo)
+            })
+            .collect();
+
+        // A single normalizing constant is shared by the whole contest
+        let sig_perf_sq = self.beta.powi(2) / params.weight;
+        let c = all_ratings
+            .iter()
+            .map(|(rating, _)| rating.sig.powi(2) + sig_perf_sq)
+            .sum::<f64>()
+            .sqrt();
+        // Shifting every exponent by the same amount leaves all the probabilities unchanged
+        let mu_max = all_ratings
+            .iter()
+            .map(|(rating, _)| rating.mu)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let strength = |rating: &Rating| ((rating.mu - mu_max) / c).exp();
+
+        // For each distinct rank, from best to worst, find the number of players at that rank
+        // and the total strength of players at that rank or worse
+        let mut by_rank: Vec<(usize, f64)> = all_ratings
+            .iter()
+            .map(|(rating, lo)| (*lo, strength(rating)))
+            .collect();
+        by_rank.sort_unstable_by_key(|&(lo, _)| lo);
+        let mut groups: Vec<(usize, usize, f64)> = vec![];
+        for &(lo, st) in by_rank.iter().rev() {
+            match groups.last_mut() {
+                Some((last_lo, count, sum)) if *last_lo == lo => {
+                    *count += 1;
+                    *sum += st;
+                }
+                _ => {
+                    let suffix = groups.last().map_or(0., |&(_, _, sum)| sum);
+                    groups.push((lo, 1, suffix + st));
+                }
+            }
+        }
+        groups.reverse();
+
+        // Prefix sums over the ranks no worse than each rank, of 1/S and 1/S^2
+        let mut prefix_inv = Vec::with_capacity(groups.len());
+        let (mut inv_sum, mut inv_sq_sum) = (0., 0.);
+        for &(lo, count, sum) in &groups {
+            inv_sum += sum.recip();
+            inv_sq_sum += sum.powi(-2);
+            prefix_inv.push((lo, count, inv_sum, inv_sq_sum));
+        }
+
+        standings.into_par_iter().for_each(|(player, my_lo, _)| {
+            let my_rating = &player.approx_posterior;
+            let old_sig_sq = my_rating.sig.powi(2);
+            let group_idx = prefix_inv
+                .binary_search_by_key(&my_lo, |&(lo, _, _, _)| lo)
+                .expect("Player's rank is missing from the groups");
+            let (_, count, inv_sum, inv_sq_sum) = prefix_inv[group_idx];
+
+            let st = strength(my_rating);
+            let update = (count as f64).recip() - st * inv_sum;
+            let info = st * inv_sum - st * st * inv_sq_sum;
+
+            // Compute new rating deviation
+            let gamma = my_rating.sig / c;
+            let info = gamma * info * old_sig_sq / (c * c);
+            let sig = my_rating.sig * self.kappa.max(1. - info).sqrt();
+
+            // Compute new rating
+            let mu = my_rating.mu + update * old_sig_sq / c;
+
+            player.update_rating(Rating { mu, sig }, 0.);
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::systems::common::rate_new_players;
+
+    #[test]
+    fn test_three_players_with_a_tie() {
+        // Computed by hand from Algorithm 2, summing over each player q ranked no worse, rather
+        // than over each rank as round_update() does
+        let expected = [
+            (1643.332518, 198.903184),
+            (1458.139858, 244.699736),
+            (1362.780438, 289.433768),
+        ];
+        let priors =
+            [(1600., 200.), (1500., 250.), (1400., 300.)].map(|(mu, sig)| Rating { mu, sig });
+        let system = PlackettLuce {
+            beta: 200.,
+            sig_drift: 0.,
+            kappa: 1e-4,
+        };
+        let posteriors = rate_new_players(&system, &priors, &[(0, 0), (1, 2), (1, 2)]);
+
+        for (actual, (mu, sig)) in posteriors.into_iter().zip(expected) {
+            assert!((actual.mu - mu).abs() < 1e-6, "{} != {}", actual.mu, mu);
+            assert!((actual.sig - sig).abs() < 1e-6, "{} != {}", actual.sig, sig);
+        }
+    }
+}
@@ -1,9 +1,10 @@
 //! This version has fewer features and optimizations than elo_mmr.rs, more
 //! closely matching the pseudocode in https://arxiv.org/abs/2101.00400
-use super::{Player, Rating, RatingSystem, SECS_PER_DAY, TanhTerm};
+use super::{InactivityDecay, Player, Rating, RatingSystem, SECS_PER_DAY, TanhTerm};
 use crate::data_processing::ContestRatingParams;
-use crate::numerical::solve_newton;
+use crate::numerical::{SolverStats, solve_newton_from_with_stats};
 use rayon::prelude::*;
+use std::sync::Mutex;
 
 fn eval_less(term: &TanhTerm, x: f64) -> (f64, f64) {
     let (val, val_prime) = term.base_values(x);
@@ -37,6 +38,8 @@ pub struct SimpleEloMMR {
     pub history_len: usize,
     // maximum number of opponents and recent events to use, as a compute-saving approximation
     pub transfer_speed: f64,
+    // if set, players who return after a long absence are first pulled toward a limit rating
+    pub inactivity_decay: Option<InactivityDecay>,
 }
 
 impl Default for SimpleEloMMR {
@@ -49,6 +52,7 @@ impl Default for SimpleEloMMR {
             split_ties: false,
             history_len: usize::MAX,
             transfer_speed: 1.,
+            inactivity_decay: None,
         }
     }
 }
@@ -73,29 +77,72 @@ impl SimpleEloMMR {
         let continuous_drift = self.drift_per_day * delta_secs / SECS_PER_DAY;
         (discrete_drift + continuous_drift).sqrt()
     }
-}
 
-impl RatingSystem for SimpleEloMMR {
-    fn individual_update(&self, params: ContestRatingParams, player: &mut Player, mu_perf: f64) {
-        let weight = self.compute_weight(params.weight, player.times_played_excl());
-        let sig_perf = self.compute_sig_perf(weight);
+    // Adds the drift that accumulates between a player's contests
+    fn apply_drift(&self, player: &mut Player, weight: f64) {
+        if let Some(inactivity_decay) = &self.inactivity_decay {
+            inactivity_decay.apply(player);
+        }
         let sig_drift = self.compute_sig_drift(weight, player.delta_time as f64);
         player.add_noise_best(sig_drift, self.transfer_speed);
+    }
 
-        player.update_rating_with_logistic(
+    // Same as individual_update(), but reporting on the solver's convergence
+    fn individual_update_with_stats(
+        &self,
+        params: ContestRatingParams,
+        player: &mut Player,
+        mu_perf: f64,
+    ) -> SolverStats {
+        let weight = self.compute_weight(params.weight, player.times_played_excl());
+        let sig_perf = self.compute_sig_perf(weight);
+        self.apply_drift(player, weight);
+
+        let result = player.update_rating_with_logistic(
             Rating {
                 mu: mu_perf,
                 sig: sig_perf,
             },
             self.history_len,
         );
+        let mut stats = SolverStats::default();
+        stats.record(&result);
+        stats
+    }
+}
+
+impl RatingSystem for SimpleEloMMR {
+    fn individual_update(&self, params: ContestRatingParams, player: &mut Player, mu_perf: f64) {
+        self.individual_update_with_stats(params, player, mu_perf);
+    }
+
+    fn infer_from_team(
+        &self,
+        params: ContestRatingParams,
+        team_before: &Player,
+        _team_after: &Player,
+        team_perf: f64,
+        member: &mut Player,
+    ) -> SolverStats {
+        let offset = member.approx_posterior.mu - team_before.approx_posterior.mu;
+        let mu_perf = (team_perf + offset).min(params.perf_ceiling);
+        self.individual_update_with_stats(params, member, mu_perf)
     }
 
     fn round_update(
         &self,
         params: ContestRatingParams,
-        mut standings: Vec<(&mut Player, usize, usize)>,
+        standings: Vec<(&mut Player, usize, usize)>,
     ) {
+        self.round_update_with_stats(params, standings, &[]);
+    }
+
+    fn round_update_with_stats(
+        &self,
+        params: ContestRatingParams,
+        mut standings: Vec<(&mut Player, usize, usize)>,
+        _scores: &[Option<f64>],
+    ) -> SolverStats {
         // Update ratings due to waiting period between contests,
         // then use it to create Gaussian terms for the Q-function.
         // The rank must also be stored in order to determine if it's a win,
@@ -105,16 +152,15 @@ impl RatingSystem for SimpleEloMMR {
             .map(|(player, _, _)| {
                 let weight = self.compute_weight(params.weight, player.times_played_excl());
                 let sig_perf = self.compute_sig_perf(weight);
-                let sig_drift = self.compute_sig_drift(weight, player.delta_time as f64);
-                player.add_noise_best(sig_drift, self.transfer_speed);
+                self.apply_drift(player, weight);
                 player.approx_posterior.with_noise(sig_perf).into()
             })
             .collect();
         let mul = if self.split_ties { 1. } else { 2. };
 
         // The computational bottleneck: update ratings based on contest performance
+        let stats = Mutex::new(SolverStats::default());
         standings.into_par_iter().for_each(|(player, lo, hi)| {
-            let bounds = (-6000.0, 9000.0);
             let f = |x| {
                 let itr1 = tanh_terms[0..lo].iter().map(|term| eval_less(term, x));
                 let itr2 = tanh_terms[lo..=hi]
@@ -125,16 +171,22 @@ impl RatingSystem for SimpleEloMMR {
                     .chain(itr3)
                     .fold((0., 0.), |(s, sp), (v, vp)| (s + v, sp + vp))
             };
-            let mu_perf = solve_newton(bounds, f).min(params.perf_ceiling);
             let weight = self.compute_weight(params.weight, player.times_played_excl());
             let sig_perf = self.compute_sig_perf(weight);
-            player.update_rating_with_logistic(
+            let perf_result = solve_newton_from_with_stats(player.approx_posterior.mu, sig_perf, f);
+            let mu_perf = perf_result.root_or_warn().min(params.perf_ceiling);
+            let rating_result = player.update_rating_with_logistic(
                 Rating {
                     mu: mu_perf,
                     sig: sig_perf,
                 },
                 self.history_len,
             );
+            let mut player_stats = SolverStats::default();
+            player_stats.record(&perf_result);
+            player_stats.record(&rating_result);
+            *stats.lock().unwrap() += player_stats;
         });
+        stats.into_inner().unwrap()
     }
 }
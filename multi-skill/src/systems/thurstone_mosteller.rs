@@ -0,0 +1,149 @@
+//! The Thurstone-Mosteller model, or Algorithm 3 (TM-Full)
+//! from https://jmlr.csail.mit.edu/papers/volume12/weng11a/weng11a.pdf
+
+use super::{Player, Rating, RatingSystem};
+use crate::data_processing::ContestRatingParams;
+use crate::numerical::{TANH_MULTIPLIER, standard_normal_cdf, standard_normal_pdf};
+use rayon::prelude::*;
+
+#[derive(Debug)]
+pub struct ThurstoneMosteller {
+    pub beta: f64,
+    pub sig_drift: f64,
+    pub kappa: f64,
+    // performance margin within which two players are considered tied
+    pub eps: f64,
+}
+
+impl Default for ThurstoneMosteller {
+    fn default() -> Self {
+        Self {
+            beta: 400. * TANH_MULTIPLIER / std::f64::consts::LN_10,
+            sig_drift: 35.,
+            kappa: 1e-4,
+            eps: 1.,
+        }
+    }
+}
+
+impl ThurstoneMosteller {
+    // Additive correction and multiplicative variance factor after a win with margin x > t
+    fn v_w_win(x: f64, t: f64) -> (f64, f64) {
+        let z = x - t;
+        let cdf = standard_normal_cdf(z);
+        // Far into the tail, phi(z)/Phi(z) approaches -z
+        let v = if cdf < 1e-300 {
+            -z
+        } else {
+            standard_normal_pdf(z) / cdf
+        };
+        (v, v * (v + z))
+    }
+
+    // Additive correction and multiplicative variance factor after a tie with |x| < t
+    fn v_w_tie(x: f64, t: f64) -> (f64, f64) {
+        let (a, b) = (-t - x, t - x);
+        // Subtract in whichever tail keeps the most precision
+        let denom = if x > 0. {
+            standard_normal_cdf(b) - standard_normal_cdf(a)
+        } else {
+            standard_normal_cdf(-a) - standard_normal_cdf(-b)
+        };
+        if denom < 1e-300 {
+            // The tie is extremely unlikely; in the limit, x is pulled to the nearest edge
+            let v = if x < 0. { a } else { b };
+            return (v, 1.);
+        }
+        let v = (standard_normal_pdf(a) - standard_normal_pdf(b)) / denom;
+        let w = v * v + (b * standard_normal_pdf(b) - a * standard_normal_pdf(a)) / denom;
+        (v, w)
+    }
+}
+
+impl RatingSystem for ThurstoneMosteller {
+    fn round_update(
+        &self,
+        params: ContestRatingParams,
+        mut standings: Vec<(&mut Player, usize, usize)>,
+    ) {
+        let all_ratings: Vec<(Rating, usize)> = standings
+            .par_iter_mut()
+            .map(|(player, lo, _)| {
+                player.add_noise_and_collapse(self.sig_drift);
+                (player.approx_posterior, *lo)
+            })
+            .collect();
+
+        let sig_perf_sq = self.beta.powi(2) / params.weight;
+        standings
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(i, (player, my_lo, _))| {
+                let my_rating = &player.approx_posterior;
+                let old_sig_sq = my_rating.sig.powi(2);
+                let mut info = 0.;
+                let mut update = 0.;
+                for (j, (rating, lo)) in all_ratings.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    let c_sq = old_sig_sq + rating.sig.powi(2) + 2. * sig_perf_sq;
+                    let c = c_sq.sqrt();
+                    let x = (my_rating.mu - rating.mu) / c;
+                    let t = self.eps / c;
+                    let (v, w) = match my_lo.cmp(lo) {
+                        std::cmp::Ordering::Less => Self::v_w_win(x, t),
+                        std::cmp::Ordering::Equal => Self::v_w_tie(x, t),
+                        std::cmp::Ordering::Greater => {
+                            let (v, w) = Self::v_w_win(-x, t);
+                            (-v, w)
+                        }
+                    };
+                    let gamma = my_rating.sig / c;
+
+                    info += gamma * w / c_sq;
+                    update += v / c;
+                }
+
+                // Compute new rating deviation
+                info *= old_sig_sq;
+                let sig = my_rating.sig * self.kappa.max(1. - info).sqrt();
+
+                // Compute new rating
+                update *= old_sig_sq;
+                let mu = my_rating.mu + update;
+
+                player.update_rating(Rating { mu, sig }, 0.);
+            });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::systems::common::rate_new_players;
+
+    #[test]
+    fn test_three_players_with_a_tie() {
+        // Computed by hand from Algorithm 3, with a margin wide enough for the tie to matter
+        let expected = [
+            (1720.407592, 188.773537),
+            (1367.160916, 214.884801),
+            (1320.371199, 232.983245),
+        ];
+        let priors =
+            [(1600., 200.), (1500., 250.), (1400., 300.)].map(|(mu, sig)| Rating { mu, sig });
+        let system = ThurstoneMosteller {
+            beta: 200.,
+            sig_drift: 0.,
+            kappa: 1e-4,
+            eps: 50.,
+        };
+        let posteriors = rate_new_players(&system, &priors, &[(0, 0), (1, 2), (1, 2)]);
+
+        for (actual, (mu, sig)) in posteriors.into_iter().zip(expected) {
+            assert!((actual.mu - mu).abs() < 1e-6, "{} != {}", actual.mu, mu);
+            assert!((actual.sig - sig).abs() < 1e-6, "{} != {}", actual.sig, sig);
+        }
+    }
+}
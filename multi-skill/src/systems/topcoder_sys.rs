@@ -26,9 +26,49 @@ impl TopcoderSys {
         let z = sqrt_weight * (player.mu - foe.mu) / player.sig.hypot(foe.sig);
         standard_normal_cdf(z)
     }
+
+    // Combines the player's current rating with their performance in the latest contest
+    fn rating_from_performance(
+        &self,
+        params: ContestRatingParams,
+        player: &Player,
+        perf_as: f64,
+    ) -> Rating {
+        let sqrt_contest_weight = params.weight.sqrt();
+        let weight_extra = self.weight_noob - self.weight_limit;
+        let old_rating = player.approx_posterior.mu;
+        let vol_sq = player.approx_posterior.sig.powi(2);
+
+        let num_contests = player.times_played() as f64;
+        let mut weight = self.weight_limit + weight_extra / num_contests;
+        let mut cap = 150. + 1500. / (num_contests + 1.);
+        cap *= sqrt_contest_weight * weight / (0.18 + 0.42 / num_contests);
+
+        weight *= params.weight / (1. - weight);
+        if old_rating >= 2500. {
+            weight *= 0.8;
+        } else if old_rating >= 2000. {
+            weight *= 0.9;
+        }
+
+        let try_rating = (old_rating + weight * perf_as) / (1. + weight);
+        let new_rating = try_rating.clamp(old_rating - cap, old_rating + cap);
+        let new_vol = ((try_rating - old_rating).powi(2) / weight + vol_sq / (1. + weight)).sqrt();
+
+        Rating {
+            mu: new_rating,
+            sig: new_vol,
+        }
+    }
 }
 
 impl RatingSystem for TopcoderSys {
+    fn individual_update(&self, params: ContestRatingParams, player: &mut Player, mu_perf: f64) {
+        let perf_as = mu_perf.min(params.perf_ceiling);
+        let rating = self.rating_from_performance(params, player, perf_as);
+        player.update_rating(rating, perf_as);
+    }
+
     fn round_update(
         &self,
         params: ContestRatingParams,
@@ -58,12 +98,10 @@ impl RatingSystem for TopcoderSys {
         };
 
         let sqrt_contest_weight = params.weight.sqrt();
-        let weight_extra = self.weight_noob - self.weight_limit;
         let new_ratings: Vec<(Rating, f64)> = standings
             .par_iter()
             .map(|(player, lo, hi)| {
                 let old_rating = player.approx_posterior.mu;
-                let vol_sq = player.approx_posterior.sig.powi(2);
 
                 let ex_rank = standings
                     .iter()
@@ -83,29 +121,8 @@ impl RatingSystem for TopcoderSys {
                 let ac_perf = -standard_normal_cdf_inv(ac_rank / num_coders);
                 let perf_as = old_rating + c_factor * (ac_perf - ex_perf);
                 let perf_as = perf_as.min(params.perf_ceiling);
-
-                let num_contests = player.times_played() as f64;
-                let mut weight = self.weight_limit + weight_extra / num_contests;
-                let mut cap = 150. + 1500. / (num_contests + 1.);
-                cap *= sqrt_contest_weight * weight / (0.18 + 0.42 / num_contests);
-
-                weight *= params.weight / (1. - weight);
-                if old_rating >= 2500. {
-                    weight *= 0.8;
-                } else if old_rating >= 2000. {
-                    weight *= 0.9;
-                }
-
-                let try_rating = (old_rating + weight * perf_as) / (1. + weight);
-                let new_rating = try_rating.clamp(old_rating - cap, old_rating + cap);
-                let new_vol =
-                    ((try_rating - old_rating).powi(2) / weight + vol_sq / (1. + weight)).sqrt();
-
                 (
-                    Rating {
-                        mu: new_rating,
-                        sig: new_vol,
-                    },
+                    self.rating_from_performance(params, player, perf_as),
                     perf_as,
                 )
             })
@@ -0,0 +1,318 @@
+//! Double-double arithmetic: each number is an unevaluated sum of two f64's, giving about 32
+//! significant decimal digits in pure Rust. The algorithms follow Hida, Li and Bailey's
+//! "Library for Double-Double and Quad-Double Arithmetic".
+use overload::overload;
+use std::fmt;
+use std::ops;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct DoubleDouble {
+    hi: f64,
+    // invariant: |lo| is at most half an ulp of hi
+    lo: f64,
+}
+
+pub const ZERO: DoubleDouble = DoubleDouble { hi: 0., lo: 0. };
+pub const ONE: DoubleDouble = DoubleDouble { hi: 1., lo: 0. };
+pub const TWO: DoubleDouble = DoubleDouble { hi: 2., lo: 0. };
+pub const PI: DoubleDouble = DoubleDouble {
+    hi: std::f64::consts::PI,
+    lo: 1.2246467991473532e-16,
+};
+const LN_2: DoubleDouble = DoubleDouble {
+    hi: std::f64::consts::LN_2,
+    lo: 2.3190468138462996e-17,
+};
+
+// Error-free transformation: s + e == a + b exactly
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+// Same as two_sum(), but assuming |a| >= |b|
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+// Error-free transformation: p + e == a * b exactly
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let p = a * b;
+    (p, a.mul_add(b, -p))
+}
+
+impl DoubleDouble {
+    pub const INFINITY: Self = Self {
+        hi: f64::INFINITY,
+        lo: 0.,
+    };
+
+    fn normalize((hi, lo): (f64, f64)) -> Self {
+        if !hi.is_finite() {
+            return hi.into();
+        }
+        let (hi, lo) = quick_two_sum(hi, lo);
+        Self { hi, lo }
+    }
+
+    // Multiplies by an exact power of two
+    fn ldexp(self, exp: i32) -> Self {
+        // Split the power in two, so that neither half overflows prematurely
+        let (a, b) = (2f64.powi(exp / 2), 2f64.powi(exp - exp / 2));
+        Self {
+            hi: self.hi * a * b,
+            lo: self.lo * a * b,
+        }
+    }
+
+    pub fn is_nan(self) -> bool {
+        self.hi.is_nan()
+    }
+
+    pub fn is_infinite(self) -> bool {
+        self.hi.is_infinite()
+    }
+
+    pub fn abs(self) -> Self {
+        if self.hi < 0. { -self } else { self }
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        if self.is_nan() || self < other {
+            other
+        } else {
+            self
+        }
+    }
+
+    pub fn recip(self) -> Self {
+        ONE / self
+    }
+
+    pub fn powi(self, n: i32) -> Self {
+        let mut base = self;
+        let mut exp = n.unsigned_abs();
+        let mut result = ONE;
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result *= base;
+            }
+            base *= base;
+            exp >>= 1;
+        }
+        if n < 0 { result.recip() } else { result }
+    }
+
+    pub fn sqrt(self) -> Self {
+        if self.hi <= 0. || !self.hi.is_finite() {
+            return self.hi.sqrt().into();
+        }
+        // One Newton step from the f64 approximation doubles the number of correct digits
+        let x = Self::from(self.hi.sqrt());
+        x + (self - x * x) / (x * 2.)
+    }
+
+    pub fn hypot(self, other: Self) -> Self {
+        if self.is_infinite() || other.is_infinite() {
+            return Self::INFINITY;
+        }
+        (self * self + other * other).sqrt()
+    }
+
+    pub fn exp(self) -> Self {
+        if self.hi > 709.8 {
+            return Self::INFINITY;
+        }
+        if self.hi < -745.2 {
+            return ZERO;
+        }
+
+        // Reduce to exp(x) = 2^k * exp(r)^1024, where |r| <= ln(2) / 2048
+        let k = (self.hi / LN_2.hi).round();
+        let r = (self - LN_2 * k).ldexp(-10);
+
+        // The Taylor series of exp(r) - 1 converges after only a few terms
+        let mut term = r;
+        let mut sum = r;
+        let mut i = 1.;
+        while term.hi.abs() > 1e-36 {
+            i += 1.;
+            term = term * r / i;
+            sum += term;
+        }
+
+        // Square 10 times, tracking exp(r) - 1 to preserve precision
+        for _ in 0..10 {
+            sum = sum * 2. + sum * sum;
+        }
+        (sum + ONE).ldexp(k as i32)
+    }
+
+    pub fn erfc(self) -> Self {
+        if self.is_nan() {
+            return self;
+        }
+        if self.hi < 0. {
+            return TWO - (-self).erfc();
+        }
+        let frac_1_sqrt_pi = PI.sqrt().recip();
+        let exp_neg_sq = (-self * self).exp();
+
+        if self.hi < 2.5 {
+            // erf(x) = 2/sqrt(pi) * exp(-x^2) * sum_n 2^n x^(2n+1) / (1 * 3 * ... * (2n+1)),
+            // a series of positive terms that doesn't suffer from cancellation
+            let two_x_sq = self * self * 2.;
+            let mut term = self;
+            let mut sum = self;
+            let mut denom = 1.;
+            while term.hi > 1e-34 * sum.hi {
+                denom += 2.;
+                term = term * two_x_sq / denom;
+                sum += term;
+            }
+            ONE - sum * exp_neg_sq * frac_1_sqrt_pi * 2.
+        } else {
+            // erfc(x) = exp(-x^2) / sqrt(pi) / (x + (1/2) / (x + 1 / (x + (3/2) / (x + ...)))),
+            // evaluated using the modified Lentz method
+            let tiny = Self::from(1e-300);
+            let mut f = self;
+            let mut c = self;
+            let mut d = ZERO;
+            for j in 1..10_000 {
+                let a = j as f64 * 0.5;
+                d = self + d * a;
+                if d == ZERO {
+                    d = tiny;
+                }
+                c = self + c.recip() * a;
+                if c == ZERO {
+                    c = tiny;
+                }
+                d = d.recip();
+                let delta = c * d;
+                f *= delta;
+                if (delta - ONE).abs().hi < 1e-33 {
+                    break;
+                }
+            }
+            exp_neg_sq * frac_1_sqrt_pi / f
+        }
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(hi: f64) -> Self {
+        Self { hi, lo: 0. }
+    }
+}
+
+impl From<DoubleDouble> for f64 {
+    fn from(x: DoubleDouble) -> Self {
+        x.hi + x.lo
+    }
+}
+
+impl fmt::Display for DoubleDouble {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&f64::from(*self), f)
+    }
+}
+
+overload!(-(a: ?DoubleDouble) -> DoubleDouble {
+    DoubleDouble { hi: -a.hi, lo: -a.lo }
+});
+
+overload!((a: ?DoubleDouble) + (b: ?DoubleDouble) -> DoubleDouble {
+    let (s, e) = two_sum(a.hi, b.hi);
+    if !s.is_finite() {
+        return s.into();
+    }
+    let (t, f) = two_sum(a.lo, b.lo);
+    let (s, e) = quick_two_sum(s, e + t);
+    DoubleDouble::normalize((s, e + f))
+});
+
+overload!((a: ?DoubleDouble) - (b: ?DoubleDouble) -> DoubleDouble {
+    a + (-b)
+});
+
+overload!((a: ?DoubleDouble) * (b: ?DoubleDouble) -> DoubleDouble {
+    let (p, e) = two_prod(a.hi, b.hi);
+    if !p.is_finite() {
+        return p.into();
+    }
+    DoubleDouble::normalize((p, e + (a.hi * b.lo + a.lo * b.hi)))
+});
+
+overload!((a: ?DoubleDouble) * (b: f64) -> DoubleDouble {
+    a * DoubleDouble::from(b)
+});
+
+overload!((a: ?DoubleDouble) / (b: ?DoubleDouble) -> DoubleDouble {
+    let q1 = a.hi / b.hi;
+    if !q1.is_finite() || b.is_infinite() {
+        return q1.into();
+    }
+    // Long division, refining the quotient with the remainder
+    let r = a - b * q1;
+    let q2 = r.hi / b.hi;
+    let r = r - b * q2;
+    let q3 = r.hi / b.hi;
+    DoubleDouble::normalize((q1, q2)) + DoubleDouble::from(q3)
+});
+
+overload!((a: ?DoubleDouble) / (b: f64) -> DoubleDouble {
+    a / DoubleDouble::from(b)
+});
+
+overload!((a: &mut DoubleDouble) += (b: ?DoubleDouble) {
+    *a = *a + b;
+});
+
+overload!((a: &mut DoubleDouble) -= (b: ?DoubleDouble) {
+    *a = *a - b;
+});
+
+overload!((a: &mut DoubleDouble) *= (b: ?DoubleDouble) {
+    *a = *a * b;
+});
+
+overload!((a: &mut DoubleDouble) /= (b: ?DoubleDouble) {
+    *a = *a / b;
+});
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(actual: DoubleDouble, hi: f64, lo: f64) {
+        let expected = DoubleDouble { hi, lo };
+        let error = ((actual - expected) / expected).abs();
+        assert!(error.hi < 1e-30, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn test_elementary_functions() {
+        assert_close(TWO.sqrt(), std::f64::consts::SQRT_2, -9.667293313452913e-17);
+        assert_close(ONE.exp(), std::f64::consts::E, 1.4456468917292502e-16);
+        assert_close(
+            (-DoubleDouble::from(30.)).exp(),
+            9.357622968840175e-14,
+            -2.1170146272646406e-30,
+        );
+        assert_close(ONE / DoubleDouble::from(3.) * 3., 1., 0.);
+    }
+
+    #[test]
+    fn test_erfc() {
+        let erfc = |x: f64| DoubleDouble::from(x).erfc();
+        assert_close(erfc(0.5), 0.4795001221869535, -1.900077467916287e-17);
+        assert_close(erfc(1.), 0.15729920705028513, -2.954563826510312e-18);
+        assert_close(erfc(-2.), 1.9953222650189528, -8.895031662953915e-17);
+        assert_close(erfc(2.5), 0.0004069520174449589, 2.080297158010754e-20);
+        assert_close(erfc(3.), 2.209049699858544e-5, 1.5563377960343457e-22);
+        assert_close(erfc(6.), 2.1519736712498913e-17, 3.1898197253599377e-34);
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(not(feature = "double-double"))]
 #[allow(dead_code)]
 mod f64_module {
     pub type MyFloat = f64;
@@ -5,19 +6,31 @@ mod f64_module {
     pub const TWO: MyFloat = 2.;
     pub use statrs::function::erf::erfc;
     pub use std::f64::consts::PI;
+    pub fn from_f64(x: f64) -> MyFloat {
+        x
+    }
+    pub fn to_f64(x: MyFloat) -> f64 {
+        x
+    }
 }
 
-/*#[allow(dead_code)]
-mod f128_module {
-    pub type MyFloat = f128::f128;
-    pub const ZERO: MyFloat = MyFloat::ZERO;
-    pub const TWO: MyFloat = MyFloat::TWO;
-    pub const PI: MyFloat = MyFloat::PI;
+#[cfg(feature = "double-double")]
+mod double_double_module {
+    pub use super::super::double_double::{PI, TWO, ZERO};
+    pub type MyFloat = super::super::double_double::DoubleDouble;
     pub fn erfc(a: MyFloat) -> MyFloat {
-        unsafe { f128::ffi::erfcq_f(a) }
+        a.erfc()
+    }
+    pub fn from_f64(x: f64) -> MyFloat {
+        x.into()
     }
-}*/
+    pub fn to_f64(x: MyFloat) -> f64 {
+        x.into()
+    }
+}
 
-// Choose between f64 and f128
+// Choose between f64 and double-double
+#[cfg(feature = "double-double")]
+pub use double_double_module::*;
+#[cfg(not(feature = "double-double"))]
 pub use f64_module::*;
-pub use num_traits::float::Float;
@@ -1,16 +1,20 @@
+#[cfg(feature = "double-double")]
+mod double_double;
 mod float;
 mod nodes;
 mod normal;
 
 use super::{Player, Rating, RatingSystem};
 use crate::data_processing::ContestRatingParams;
+use crate::numerical::SolverStats;
 
-use nodes::{FuncNode, GreaterNode, LeqNode, ProdNode, SumNode, TreeNode, ValueNode};
+use nodes::{
+    EdgeId, FuncNode, GreaterNode, LeqNode, Messages, ProdNode, SumNode, TreeNode, ValueNode,
+};
 use normal::Gaussian;
 
-use float::{Float, MyFloat, ZERO};
-use std::cell::RefCell;
-use std::rc::Rc;
+use float::{MyFloat, ZERO, from_f64, to_f64};
+use rayon::prelude::*;
 
 type TSMessage = nodes::Message;
 type TSPlayer<'a> = (&'a mut Player, Gaussian);
@@ -42,65 +46,40 @@ impl Default for TrueSkillSPb {
     }
 }
 
-fn gen_team_message<T, K: Clone>(places: &[Vec<T>], default: &K) -> Vec<Vec<K>> {
-    places
-        .iter()
-        .map(|place| vec![default.clone(); place.len()])
-        .collect()
+// Passes messages from a whole layer of nodes at once, in parallel
+fn infer<N: TreeNode>(nodes: &[N], messages: &mut Messages) {
+    let updates: Vec<_> = nodes
+        .par_iter()
+        .flat_map_iter(|node| node.infer(messages))
+        .collect();
+    messages.send::<N>(updates);
 }
 
-fn gen_player_message<T, K: Clone>(places: &[Vec<Vec<T>>], default: &K) -> Vec<Vec<Vec<K>>> {
-    places
-        .iter()
-        .map(|place| {
-            place
-                .iter()
-                .map(|team| vec![default.clone(); team.len()])
-                .collect()
-        })
-        .collect()
+fn infer_one<N: TreeNode>(node: &N, messages: &mut Messages) {
+    let updates = node.infer(messages);
+    messages.send::<N>(updates);
 }
 
-fn infer1(who: &mut Vec<impl TreeNode>) {
-    for item in who {
-        item.infer();
-    }
-}
-
-fn infer2(who: &mut Vec<Vec<impl TreeNode>>) {
-    for item in who {
-        infer1(item);
-    }
-}
-
-fn infer3(who: &mut Vec<Vec<Vec<impl TreeNode>>>) {
-    for item in who {
-        infer2(item);
-    }
-}
-
-fn infer_ld(ld: &mut Vec<impl TreeNode>, l: &mut Vec<impl TreeNode>) {
+// The chain of places must be traversed in order, forward and then backward
+fn infer_ld(ld: &[SumNode], l: &[ProdNode], messages: &mut Messages) {
     for i in 0..ld.len() {
-        l[i].infer();
-        ld[i].infer();
+        infer_one(&l[i], messages);
+        infer_one(&ld[i], messages);
     }
-    l.last_mut().unwrap().infer();
+    infer_one(l.last().unwrap(), messages);
     for i in (0..ld.len()).rev() {
-        ld[i].infer();
-        l[i].infer();
+        infer_one(&ld[i], messages);
+        infer_one(&l[i], messages);
     }
 }
 
-fn check_convergence(
-    a: &[Rc<RefCell<(TSMessage, TSMessage)>>],
-    b: &[(TSMessage, TSMessage)],
-) -> MyFloat {
+fn check_convergence(messages: &Messages, a: &[EdgeId], b: &[(TSMessage, TSMessage)]) -> MyFloat {
     if a.len() != b.len() {
         return MyFloat::INFINITY;
     }
 
     a.iter()
-        .map(|ai| ai.borrow())
+        .map(|&edge| (&messages.to_value[edge], &messages.to_func[edge]))
         .zip(b.iter())
         .flat_map(|(ai, bi)| {
             vec![
@@ -121,16 +100,19 @@ impl TrueSkillSPb {
             return;
         }
 
-        // could be optimized, written that way for simplicity
+        // Value nodes are stored in flat arrays, and all messages in a single arena, so that
+        // each layer of the factor graph can pass its messages in parallel.
         // TODO: invent better variable names
         let sig_perf = self.beta / contest_weight.sqrt();
-        let mut s = gen_player_message(contest, &ProdNode::new());
-        let mut perf = gen_player_message(contest, &ProdNode::new());
-        let mut p = gen_player_message(contest, &ProdNode::new());
-        let mut t = gen_team_message(contest, &ProdNode::new());
-        let mut u = gen_team_message(contest, &LeqNode::new(self.eps));
+        let mut messages = Messages::default();
+        let mut s = vec![];
+        let mut p = vec![];
+        let mut t = vec![];
+        let mut u = vec![];
         let mut l = vec![ProdNode::new(); contest.len()];
-        let mut d = vec![GreaterNode::new(2. * self.eps); contest.len() - 1];
+        let mut d: Vec<_> = (1..contest.len())
+            .map(|_| GreaterNode::new(2. * self.eps, &mut messages))
+            .collect();
         let mut sp = vec![];
         let mut pt = vec![];
         let mut tul = vec![];
@@ -140,90 +122,107 @@ impl TrueSkillSPb {
         let mut old_conv = vec![];
 
         for i in 0..contest.len() {
-            for j in 0..contest[i].len() {
-                for k in 0..contest[i][j].len() {
-                    let new_edge = s[i][j][k].add_edge();
-
-                    new_edge.upgrade().unwrap().borrow_mut().0 = contest[i][j][k].1.clone();
-
-                    sp.push(SumNode::new(&mut [
-                        &mut p[i][j][k],
-                        &mut s[i][j][k],
-                        &mut perf[i][j][k],
-                    ]));
-                    RefCell::borrow_mut(perf[i][j][k].get_edges_mut().last_mut().unwrap()).1 =
-                        Gaussian {
-                            mu: ZERO,
-                            sigma: sig_perf.into(),
-                        };
-
-                    players.push((i, j, k, new_edge));
+            for team in &contest[i] {
+                let mut team_p = vec![];
+                for (_, prior) in team {
+                    let mut s_node = ProdNode::new();
+                    let mut p_node = ProdNode::new();
+                    // The performance noise sends a fixed message, so it never needs to infer
+                    let mut perf_node = ProdNode::new();
+
+                    let new_edge = s_node.add_edge(&mut messages);
+                    messages.to_value[new_edge] = prior.clone();
+
+                    sp.push(SumNode::new(
+                        &mut [&mut p_node, &mut s_node, &mut perf_node],
+                        &mut messages,
+                    ));
+                    messages.to_func[*perf_node.get_edges().last().unwrap()] = Gaussian {
+                        mu: ZERO,
+                        sigma: from_f64(sig_perf),
+                    };
+
+                    s.push(s_node);
+                    team_p.push(p_node);
+                    players.push(new_edge);
                 }
 
-                let mut tt: Vec<&mut dyn ValueNode> = vec![&mut t[i][j]];
-                tt.extend(p[i][j].iter_mut().map(|pp| pp as &mut dyn ValueNode));
-
-                pt.push(SumNode::new(&mut tt));
-                tul.push(SumNode::new(&mut [&mut l[i], &mut t[i][j], &mut u[i][j]]));
-                conv.push(t[i][j].get_edges().last().unwrap().clone());
+                let mut t_node = ProdNode::new();
+                let mut u_node = LeqNode::new(self.eps, &mut messages);
+                let mut tt: Vec<&mut dyn ValueNode> = vec![&mut t_node];
+                tt.extend(team_p.iter_mut().map(|pp| pp as &mut dyn ValueNode));
+
+                pt.push(SumNode::new(&mut tt, &mut messages));
+                tul.push(SumNode::new(
+                    &mut [&mut l[i], &mut t_node, &mut u_node],
+                    &mut messages,
+                ));
+                conv.push(*t_node.get_edges().last().unwrap());
+
+                p.extend(team_p);
+                t.push(t_node);
+                u.push(u_node);
             }
 
             if i != 0 {
                 match &mut l[i - 1..=i] {
                     [a, b] => {
-                        ld.push(SumNode::new(&mut [a, b, &mut d[i - 1]]));
+                        ld.push(SumNode::new(&mut [a, b, &mut d[i - 1]], &mut messages));
                     }
                     _ => panic!("Must have 0 < i < l.len()"),
                 };
             }
         }
 
-        infer3(&mut s);
-        infer1(&mut sp);
-        infer3(&mut p);
-        infer1(&mut pt);
-        infer2(&mut t);
-        infer1(&mut tul);
-        infer2(&mut u);
-        infer1(&mut tul);
+        infer(&s, &mut messages);
+        infer(&sp, &mut messages);
+        infer(&p, &mut messages);
+        infer(&pt, &mut messages);
+        infer(&t, &mut messages);
+        infer(&tul, &mut messages);
+        infer(&u, &mut messages);
+        infer(&tul, &mut messages);
 
         let mut rounds = 0;
         let max_rounds = 42;
         while rounds < max_rounds
-            && check_convergence(&conv, &old_conv) >= self.convergence_eps.into()
+            && check_convergence(&messages, &conv, &old_conv) >= from_f64(self.convergence_eps)
         {
             old_conv.clear();
-            for item in &conv {
-                old_conv.push(RefCell::borrow(item).clone());
+            for &edge in &conv {
+                old_conv.push((
+                    messages.to_value[edge].clone(),
+                    messages.to_func[edge].clone(),
+                ));
             }
             rounds += 1;
 
-            infer_ld(&mut ld, &mut l);
-            infer1(&mut d);
-            infer_ld(&mut ld, &mut l);
-            infer1(&mut tul);
-            infer2(&mut u);
-            infer1(&mut tul);
+            infer_ld(&ld, &l, &mut messages);
+            infer(&d, &mut messages);
+            infer_ld(&ld, &l, &mut messages);
+            infer(&tul, &mut messages);
+            infer(&u, &mut messages);
+            infer(&tul, &mut messages);
         }
 
         //tracing::trace!("Rounds until convergence: {}", rounds);
 
-        infer2(&mut t);
-        infer1(&mut pt);
-        infer3(&mut p);
-        infer1(&mut sp);
-        infer3(&mut s);
+        infer(&t, &mut messages);
+        infer(&pt, &mut messages);
+        infer(&p, &mut messages);
+        infer(&sp, &mut messages);
+        infer(&s, &mut messages);
 
-        for (i, j, k, mess) in players {
-            let val = mess.upgrade().unwrap();
-            let (prior, performance) = &*val.borrow();
-            let (player, gaussian) = &mut contest[i][j][k];
+        let entrants = contest.iter_mut().flatten().flatten();
+        for ((player, gaussian), edge) in entrants.zip(players) {
+            let prior = &messages.to_value[edge];
+            let performance = &messages.to_func[edge];
 
             *gaussian = prior * performance;
             player.update_rating(
                 Rating {
-                    mu: gaussian.mu.into(),
-                    sig: gaussian.sigma.into(),
+                    mu: to_f64(gaussian.mu),
+                    sig: to_f64(gaussian.sigma),
                 },
                 0.,
             );
@@ -232,6 +231,48 @@ impl TrueSkillSPb {
 }
 
 impl RatingSystem for TrueSkillSPb {
+    // A team's performance is the sum of its members' performances, each with its own drift and
+    // performance noise. Since round_update() adds these only once per team, we fold the rest of
+    // the members' drift and noise into the team's prior.
+    fn make_team(&self, params: ContestRatingParams, members: &[&Player]) -> Player {
+        let sig_perf_sq = self.beta.powi(2) / params.weight;
+        let mu = members.iter().map(|m| m.approx_posterior.mu).sum::<f64>();
+        let sig_sq = members
+            .iter()
+            .map(|m| m.approx_posterior.sig.powi(2) + self.sig_drift.powi(2) + sig_perf_sq)
+            .sum::<f64>()
+            - self.sig_drift.powi(2)
+            - sig_perf_sq;
+        Player::for_team(mu, sig_sq.sqrt(), members)
+    }
+
+    // Exact Gaussian conditioning of one summand on the posterior of the sum
+    fn infer_from_team(
+        &self,
+        _params: ContestRatingParams,
+        team_before: &Player,
+        team_after: &Player,
+        _team_perf: f64,
+        member: &mut Player,
+    ) -> SolverStats {
+        let prior = member.approx_posterior.with_noise(self.sig_drift);
+        let team_prior = team_before.approx_posterior.with_noise(self.sig_drift);
+        let team_posterior = team_after.approx_posterior;
+
+        let gain = (prior.sig / team_prior.sig).powi(2);
+        let mu = prior.mu + gain * (team_posterior.mu - team_prior.mu);
+        let sig_sq =
+            prior.sig.powi(2) - gain * gain * (team_prior.sig.powi(2) - team_posterior.sig.powi(2));
+        member.update_rating(
+            Rating {
+                mu,
+                sig: sig_sq.sqrt(),
+            },
+            0.,
+        );
+        SolverStats::default()
+    }
+
     fn round_update(
         &self,
         params: ContestRatingParams,
@@ -250,8 +291,8 @@ impl RatingSystem for TrueSkillSPb {
             }
             let noised = user.approx_posterior.with_noise(self.sig_drift);
             let gaussian = Gaussian {
-                mu: noised.mu.into(),
-                sigma: noised.sig.into(),
+                mu: from_f64(noised.mu),
+                sigma: from_f64(noised.sig),
             };
             contest.last_mut().unwrap().push(vec![(user, gaussian)]);
             prev = lo;
@@ -261,3 +302,156 @@ impl RatingSystem for TrueSkillSPb {
         self.inference(params.weight, &mut contest);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::data_processing::Contest;
+    use crate::systems::common::rate_new_players;
+    use crate::systems::{PlayerEvent, PlayersByName, simulate_contest};
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_symmetric_contest() {
+        let priors = [Rating {
+            mu: 1500.,
+            sig: 300.,
+        }; 6];
+        let places: Vec<_> = (0..6).map(|i| (i, i)).collect();
+        let players = rate_new_players(&TrueSkillSPb::default(), &priors, &places);
+
+        // With identical priors, the updates mirror each other about the middle of the standings
+        for (first, last) in players.iter().zip(players.iter().rev()) {
+            assert!((first.mu - 1500. - (1500. - last.mu)).abs() < 1e-6);
+            assert!((first.sig - last.sig).abs() < 1e-6);
+        }
+        assert!(players[0].mu > players[1].mu);
+    }
+
+    #[test]
+    fn test_mirrored_contest() {
+        // Negating every skill and reversing the standings maps the model to itself, so the
+        // posteriors should mirror too. This fails if the tie or gap factors share one edge,
+        // which would make every gap use the message computed for the last one.
+        let priors = [(1500., 300.), (1600., 250.), (1900., 100.)];
+        let places = [(0, 0), (1, 1), (2, 2)];
+        let ratings: Vec<Rating> = priors.iter().map(|&(mu, sig)| Rating { mu, sig }).collect();
+        let mirrored: Vec<Rating> = ratings
+            .iter()
+            .rev()
+            .map(|rating| Rating {
+                mu: 3000. - rating.mu,
+                sig: rating.sig,
+            })
+            .collect();
+        let system = TrueSkillSPb::default();
+        let posteriors = rate_new_players(&system, &ratings, &places);
+        let mirrored_posteriors = rate_new_players(&system, &mirrored, &places);
+
+        for (actual, mirrored) in posteriors.iter().zip(mirrored_posteriors.iter().rev()) {
+            assert!((actual.mu - (3000. - mirrored.mu)).abs() < 1e-6);
+            assert!((actual.sig - mirrored.sig).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_matches_reference_graph() {
+        // Posteriors from the baseline implementation (commit 9c2c561), whose nodes shared their
+        // messages through Rc<RefCell<_>> and passed them one node at a time. Its LeqNodes came
+        // from gen_team_message() and its GreaterNodes from vec![], which clone a single node
+        // and hence its edge; these values come from it with each node constructed separately.
+        let expected = [
+            (1417.851353, 108.139020),
+            (1623.592940, 121.154834),
+            (1513.015928, 132.944073),
+            (1646.497706, 142.985183),
+            (1502.882631, 152.447549),
+            (1426.859769, 159.427653),
+            (1532.682733, 165.243494),
+            (1336.096059, 194.321857),
+        ];
+        let places = [
+            (0, 0),
+            (1, 2),
+            (1, 2),
+            (3, 3),
+            (4, 6),
+            (4, 6),
+            (4, 6),
+            (7, 7),
+        ];
+        let priors: Vec<Rating> = (0..places.len())
+            .map(|i| Rating {
+                mu: 1300. + 70. * ((i * 5) % 8) as f64,
+                sig: 120. + 25. * i as f64,
+            })
+            .collect();
+        let posteriors = rate_new_players(&TrueSkillSPb::default(), &priors, &places);
+
+        for (actual, (mu, sig)) in posteriors.into_iter().zip(expected) {
+            assert!((actual.mu - mu).abs() < 1e-5, "{} != {}", actual.mu, mu);
+            assert!((actual.sig - sig).abs() < 1e-5, "{} != {}", actual.sig, sig);
+        }
+    }
+
+    #[test]
+    fn test_teams_match_member_graph() {
+        let system = TrueSkillSPb::default();
+        let priors = [
+            ("alice", 1500., 300.),
+            ("bob", 1700., 200.),
+            ("carol", 1600., 250.),
+        ];
+        let mut contest = Contest::new(0);
+        contest.rating_params.weight = 0.5;
+        contest.push_team("team", vec!["alice".into(), "bob".into()]);
+        contest.push_contestant("carol");
+
+        // Rate the team through its collapsed pseudo-player
+        let mut players = PlayersByName::new();
+        for &(handle, mu, sig) in &priors {
+            let player = Player::with_rating(mu, sig, 0);
+            players.insert(handle.into(), RefCell::new(player));
+        }
+        simulate_contest(&mut players, &contest, &system, 1500., 350., 0);
+
+        // Rate the same contest with a factor graph in which each member performs separately
+        let mut members: Vec<Player> = priors
+            .iter()
+            .map(|&(_, mu, sig)| {
+                let mut player = Player::with_rating(mu, sig, 0);
+                player.event_history.push(PlayerEvent {
+                    contest_index: 0,
+                    rating_mu: 0,
+                    rating_sig: 0,
+                    perf_score: 0,
+                    place: 0,
+                });
+                player
+            })
+            .collect();
+        let mut entries = members.iter_mut().map(|player| {
+            let noised = player.approx_posterior.with_noise(system.sig_drift);
+            let gaussian = Gaussian {
+                mu: from_f64(noised.mu),
+                sigma: from_f64(noised.sig),
+            };
+            (player, gaussian)
+        });
+        let team = vec![entries.next().unwrap(), entries.next().unwrap()];
+        let mut ts_contest = vec![vec![team], vec![vec![entries.next().unwrap()]]];
+        system.inference(contest.rating_params.weight, &mut ts_contest);
+
+        for (&(handle, _, _), expected) in priors.iter().zip(&members) {
+            let actual = players[handle].borrow().approx_posterior;
+            let expected = expected.approx_posterior;
+            assert!(
+                (actual.mu - expected.mu).abs() < 1e-6,
+                "{} != {}",
+                actual.mu,
+                expected.mu
+            );
+            assert!((actual.sig - expected.sig).abs() < 1e-6);
+        }
+    }
+}
@@ -1,86 +1,111 @@
-use super::float::MyFloat;
+use super::float::{MyFloat, from_f64};
 use super::normal::{G_ONE, G_ZERO, Gaussian};
-use std::cell::RefCell;
-use std::rc::{Rc, Weak};
 
 pub type Message = Gaussian;
+pub type EdgeId = usize;
+
+/// The message store of a factor graph. Every edge joins a value node to a function node, and
+/// carries one message in each direction. Nodes refer to their edges by index into this arena.
+#[derive(Default)]
+pub struct Messages {
+    // messages from function nodes into value nodes
+    pub to_value: Vec<Message>,
+    // messages from value nodes into function nodes
+    pub to_func: Vec<Message>,
+}
+
+impl Messages {
+    fn add_edge(&mut self, to_value: Message, to_func: Message) -> EdgeId {
+        self.to_value.push(to_value);
+        self.to_func.push(to_func);
+        self.to_value.len() - 1
+    }
+
+    /// Writes updates computed by a batch of nodes of type N
+    pub fn send<N: TreeNode>(&mut self, updates: impl IntoIterator<Item = (EdgeId, Message)>) {
+        let outbox = N::outbox(self);
+        for (edge, message) in updates {
+            outbox[edge] = message;
+        }
+    }
+}
+
+pub trait TreeNode: Sync {
+    /// Computes the messages that this node sends along its edges. Nodes only read messages
+    /// sent toward them, so any set of nodes of the same type may infer in parallel.
+    fn infer(&self, messages: &Messages) -> Vec<(EdgeId, Message)>;
 
-pub trait TreeNode {
-    fn infer(&mut self);
+    /// The side of the message store that this type of node writes to
+    fn outbox(messages: &mut Messages) -> &mut [Message];
 }
 
-pub trait ValueNode: TreeNode {
-    fn add_edge(&mut self) -> Weak<RefCell<(Message, Message)>>;
+pub trait ValueNode {
+    fn add_edge(&mut self, messages: &mut Messages) -> EdgeId;
 }
 
 pub trait FuncNode: TreeNode {
-    fn new(neighbours: &mut [&mut dyn ValueNode]) -> Self;
+    fn new(neighbours: &mut [&mut dyn ValueNode], messages: &mut Messages) -> Self;
 }
 
 #[derive(Clone)]
 pub struct ProdNode {
-    edges: Vec<Rc<RefCell<(Message, Message)>>>,
+    edges: Vec<EdgeId>,
 }
 
 #[derive(Clone)]
 pub struct LeqNode {
     eps: MyFloat,
-    edge: Rc<RefCell<(Message, Message)>>,
+    edge: EdgeId,
 }
 
 #[derive(Clone)]
 pub struct GreaterNode {
     eps: MyFloat,
-    edge: Rc<RefCell<(Message, Message)>>,
+    edge: EdgeId,
 }
 
 #[derive(Clone)]
 pub struct SumNode {
-    out_edge: Weak<RefCell<(Message, Message)>>,
-    sum_edges: Vec<Weak<RefCell<(Message, Message)>>>,
+    out_edge: EdgeId,
+    sum_edges: Vec<EdgeId>,
 }
 
 impl TreeNode for ProdNode {
-    fn infer(&mut self) {
-        fn get_prefix_prods(from: &[Rc<RefCell<(Message, Message)>>]) -> Vec<Message> {
-            let mut prefix_prods = Vec::with_capacity(from.len() + 1);
-            prefix_prods.push(G_ONE);
-
-            for val in from {
-                let (ref val, _) = *val.borrow();
-                prefix_prods.push(prefix_prods.last().unwrap() * val);
-            }
-
-            prefix_prods
+    fn infer(&self, messages: &Messages) -> Vec<(EdgeId, Message)> {
+        let incoming = |&edge: &EdgeId| &messages.to_value[edge];
+
+        // suffix_prods[i] is the product of the messages along edges i and later
+        let mut suffix_prods = Vec::with_capacity(self.edges.len() + 1);
+        suffix_prods.push(G_ONE);
+        for val in self.edges.iter().rev().map(incoming) {
+            suffix_prods.push(suffix_prods.last().unwrap() * val);
         }
-
-        let prefix_prods = get_prefix_prods(self.edges.as_slice());
-
-        self.edges.reverse();
-        let mut suffix_prods = get_prefix_prods(self.edges.as_slice());
-        self.edges.reverse();
         suffix_prods.reverse();
-        let suffix_prods = suffix_prods;
 
-        for i in 0..self.edges.len() {
-            RefCell::borrow_mut(&self.edges[i]).1 = &prefix_prods[i] * &suffix_prods[i + 1];
+        let mut prefix_prod = G_ONE;
+        let mut updates = Vec::with_capacity(self.edges.len());
+        for (i, edge) in self.edges.iter().enumerate() {
+            updates.push((*edge, &prefix_prod * &suffix_prods[i + 1]));
+            prefix_prod *= incoming(edge);
         }
+        updates
+    }
+
+    fn outbox(messages: &mut Messages) -> &mut [Message] {
+        &mut messages.to_func
     }
 }
 
 impl ValueNode for ProdNode {
-    fn add_edge(&mut self) -> Weak<RefCell<(Message, Message)>> {
-        self.edges.push(Rc::new(RefCell::new((G_ONE, G_ZERO))));
-        Rc::downgrade(&self.edges.last().unwrap())
+    fn add_edge(&mut self, messages: &mut Messages) -> EdgeId {
+        let edge = messages.add_edge(G_ONE, G_ZERO);
+        self.edges.push(edge);
+        edge
     }
 }
 
 impl ProdNode {
-    pub fn get_edges_mut(&mut self) -> &mut Vec<Rc<RefCell<(Message, Message)>>> {
-        &mut self.edges
-    }
-
-    pub fn get_edges(&self) -> &Vec<Rc<RefCell<(Message, Message)>>> {
+    pub fn get_edges(&self) -> &[EdgeId] {
         &self.edges
     }
 
@@ -90,102 +115,98 @@ impl ProdNode {
 }
 
 impl TreeNode for LeqNode {
-    fn infer(&mut self) {
-        let ans;
-        {
-            ans = RefCell::borrow(&self.edge).0.leq_eps(self.eps);
-        }
-        RefCell::borrow_mut(&self.edge).1 = ans;
+    fn infer(&self, messages: &Messages) -> Vec<(EdgeId, Message)> {
+        let ans = messages.to_value[self.edge].leq_eps(self.eps);
+        vec![(self.edge, ans)]
+    }
+
+    fn outbox(messages: &mut Messages) -> &mut [Message] {
+        &mut messages.to_func
     }
 }
 
 impl ValueNode for LeqNode {
-    fn add_edge(&mut self) -> Weak<RefCell<(Message, Message)>> {
-        Rc::downgrade(&self.edge)
+    fn add_edge(&mut self, _messages: &mut Messages) -> EdgeId {
+        self.edge
     }
 }
 
 impl LeqNode {
-    pub fn new(eps: f64) -> LeqNode {
+    pub fn new(eps: f64, messages: &mut Messages) -> LeqNode {
         LeqNode {
-            eps: eps.into(),
-            edge: Rc::new(RefCell::new((G_ZERO, G_ZERO))),
+            eps: from_f64(eps),
+            edge: messages.add_edge(G_ZERO, G_ZERO),
         }
     }
 }
 
 impl TreeNode for GreaterNode {
-    fn infer(&mut self) {
-        let ans;
-        {
-            ans = RefCell::borrow(&self.edge).0.greater_eps(self.eps);
-        }
-        RefCell::borrow_mut(&self.edge).1 = ans;
+    fn infer(&self, messages: &Messages) -> Vec<(EdgeId, Message)> {
+        let ans = messages.to_value[self.edge].greater_eps(self.eps);
+        vec![(self.edge, ans)]
+    }
+
+    fn outbox(messages: &mut Messages) -> &mut [Message] {
+        &mut messages.to_func
     }
 }
 
 impl ValueNode for GreaterNode {
-    fn add_edge(&mut self) -> Weak<RefCell<(Message, Message)>> {
-        Rc::downgrade(&self.edge)
+    fn add_edge(&mut self, _messages: &mut Messages) -> EdgeId {
+        self.edge
     }
 }
 
 impl GreaterNode {
-    pub fn new(eps: f64) -> GreaterNode {
+    pub fn new(eps: f64, messages: &mut Messages) -> GreaterNode {
         GreaterNode {
-            eps: eps.into(),
-            edge: Rc::new(RefCell::new((G_ZERO, G_ZERO))),
+            eps: from_f64(eps),
+            edge: messages.add_edge(G_ZERO, G_ZERO),
         }
     }
 }
 
 impl FuncNode for SumNode {
-    fn new(neighbours: &mut [&mut dyn ValueNode]) -> Self {
+    fn new(neighbours: &mut [&mut dyn ValueNode], messages: &mut Messages) -> Self {
         assert!(!neighbours.is_empty());
 
         let sum_edges: Vec<_> = neighbours
             .iter_mut()
             .skip(1)
-            .map(|nb| nb.add_edge())
+            .map(|nb| nb.add_edge(messages))
             .collect();
 
         SumNode {
-            out_edge: neighbours[0].add_edge(),
+            out_edge: neighbours[0].add_edge(messages),
             sum_edges,
         }
     }
 }
 
 impl TreeNode for SumNode {
-    fn infer(&mut self) {
-        fn get_prefix_sums(from: &[Weak<RefCell<(Message, Message)>>]) -> Vec<Message> {
-            let mut prefix_sums = Vec::with_capacity(from.len() + 1);
-            prefix_sums.push(G_ZERO);
-
-            for val in from {
-                let val = val.upgrade().unwrap();
-                let (_, ref val) = *val.borrow();
-                prefix_sums.push(prefix_sums.last().unwrap() + val);
-            }
-
-            prefix_sums
+    fn infer(&self, messages: &Messages) -> Vec<(EdgeId, Message)> {
+        let incoming = |&edge: &EdgeId| &messages.to_func[edge];
+
+        // suffix_sums[i] is the sum of the messages along edges i and later
+        let mut suffix_sums = Vec::with_capacity(self.sum_edges.len() + 1);
+        suffix_sums.push(G_ZERO);
+        for val in self.sum_edges.iter().rev().map(incoming) {
+            suffix_sums.push(suffix_sums.last().unwrap() + val);
         }
-
-        let prefix_sums = get_prefix_sums(self.sum_edges.as_slice());
-        self.sum_edges.reverse();
-        let mut suffix_sums = get_prefix_sums(self.sum_edges.as_slice());
-        self.sum_edges.reverse();
         suffix_sums.reverse();
-        let suffix_sums = suffix_sums;
 
-        RefCell::borrow_mut(&self.out_edge.upgrade().unwrap()).0 =
-            prefix_sums.last().unwrap().clone();
-
-        for i in 0..self.sum_edges.len() {
-            RefCell::borrow_mut(&self.sum_edges[i].upgrade().unwrap()).0 =
-                &RefCell::borrow(&self.out_edge.upgrade().unwrap()).1
-                    - &prefix_sums[i]
-                    - &suffix_sums[i + 1];
+        let out = incoming(&self.out_edge);
+        let mut prefix_sum = G_ZERO;
+        let mut updates = Vec::with_capacity(self.sum_edges.len() + 1);
+        for (i, edge) in self.sum_edges.iter().enumerate() {
+            updates.push((*edge, out - &prefix_sum - &suffix_sums[i + 1]));
+            prefix_sum += incoming(edge);
         }
+        updates.push((self.out_edge, prefix_sum));
+        updates
+    }
+
+    fn outbox(messages: &mut Messages) -> &mut [Message] {
+        &mut messages.to_value
     }
 }
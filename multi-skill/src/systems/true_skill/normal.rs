@@ -1,4 +1,4 @@
-use super::float::{Float, MyFloat, PI, TWO, ZERO, erfc};
+use super::float::{MyFloat, PI, TWO, ZERO, erfc, from_f64};
 use overload::overload;
 use std::ops;
 
@@ -144,7 +144,7 @@ impl Gaussian {
         let alpha = moment0(self.mu, self.sigma, -eps) - moment0(self.mu, self.sigma, eps);
 
         const FLOAT_CMP_EPS: f64 = 1e-8;
-        let (mu, sigma) = if alpha < FLOAT_CMP_EPS.into() {
+        let (mu, sigma) = if alpha < from_f64(FLOAT_CMP_EPS) {
             (eps, eps)
         } else {
             let mu =
@@ -175,7 +175,7 @@ impl Gaussian {
         let alpha = moment0(self.mu, self.sigma, eps);
 
         const FLOAT_CMP_EPS: f64 = 1e-8;
-        let (mu, sigma) = if alpha < FLOAT_CMP_EPS.into() {
+        let (mu, sigma) = if alpha < from_f64(FLOAT_CMP_EPS) {
             (eps, eps)
         } else {
             let mu = moment1(self.mu, self.sigma, eps) / alpha;